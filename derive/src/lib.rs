@@ -1,62 +1,25 @@
 //! Implementation of a macro creating the template specification.
 //!
 //! Some code is taken from [pest](https://github.com/pest-parser/pest/).
-#![recursion_limit = "256"]
+#![recursion_limit = "512"]
 
 extern crate proc_macro;
 extern crate proc_macro2;
+use mwparser_utils_spec::{
+    check_spec, load_files, merge_files, parse_predicate, AttributeOrigin, LoadError, LoadedSpec,
+    PredicateExpr, SpecAttribute, SpecDefault, SpecDeprecation, SpecError, SpecFile, SpecFormat,
+    SpecPriority, SpecSeverity, SpecTemplate, SpecType, BUILTIN_PREFIX,
+};
 use proc_macro2::{Span, TokenStream};
 use quote::quote;
+use std::collections::BTreeMap;
 use std::env;
-use std::fs;
-use std::io;
-use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use syn::{Ident, LitStr};
 
-mod spec;
-
-use crate::spec::{SpecFormat, SpecPriority, SpecTemplate};
-
-fn check_template(template: &SpecTemplate) -> (Ident, Vec<LitStr>, Ident, LitStr) {
-    let first_uppercase = template
-        .identifier
-        .chars()
-        .next()
-        .map(|c| c.is_uppercase())
-        .unwrap_or(false);
-
-    if !first_uppercase {
-        panic!(
-            "first character of identifier {:?} \
-             should be uppercase!",
-            template.identifier
-        );
-    }
-
-    if template.names.is_empty() {
-        panic!(
-            "{:?}: templates must have at least one name!",
-            template.identifier
-        );
-    }
-
-    for child in &template.attributes {
-        if child.identifier.chars().any(|c| c.is_uppercase()) {
-            panic!(
-                "{:?}: attribute identifiers should be lowercase!",
-                child.identifier
-            )
-        }
-
-        if child.names.is_empty() {
-            panic!(
-                "{:?}: attributes must have at least one name!",
-                child.identifier
-            );
-        }
-    }
-
+/// Create tokens for the name, alternative names, format and description of a template.
+/// The specification must have been checked with `check_spec` beforehand.
+fn template_tokens(template: &SpecTemplate) -> (Ident, Vec<LitStr>, Ident, LitStr) {
     let name: Ident = Ident::new(&template.identifier, Span::call_site());
     let names = str_to_lower_lit(&template.names);
     let format = match template.format {
@@ -72,7 +35,7 @@ fn implement_template_id(templates: &[SpecTemplate]) -> TokenStream {
     let variants: Vec<Ident> = templates
         .iter()
         .map(|template| {
-            let (name, _, _, _) = check_template(template);
+            let (name, _, _, _) = template_tokens(template);
             name
         })
         .collect();
@@ -85,6 +48,8 @@ fn implement_template_id(templates: &[SpecTemplate]) -> TokenStream {
     let dsc_variants = variants.iter();
     let names_variants = variants.iter();
     let p_variants = variants.iter();
+    let tpl_variants = variants.iter();
+    let pos_variants = variants.iter();
 
     quote! {
         /// The available template types.
@@ -109,6 +74,11 @@ fn implement_template_id(templates: &[SpecTemplate]) -> TokenStream {
                     #( KnownTemplate::#names_variants(ref t) => &t.names ),*
                 }
             }
+            pub fn position(&self) -> &Span {
+                match *self {
+                    #( KnownTemplate::#pos_variants(ref t) => &t.position ),*
+                }
+            }
             pub fn present(&self) -> &Vec<Attribute<'e>> {
                 match *self {
                     #( KnownTemplate::#p_variants(ref t) => &t.present ),*
@@ -122,6 +92,14 @@ fn implement_template_id(templates: &[SpecTemplate]) -> TokenStream {
                 }
                 None
             }
+            pub fn to_template(&self) -> Template {
+                match *self {
+                    #( KnownTemplate::#tpl_variants(ref t) => t.to_template() ),*
+                }
+            }
+            pub fn to_wikitext(&self) -> String {
+                to_wikitext(&[Element::Template(self.to_template())])
+            }
         }
     }
 }
@@ -133,29 +111,253 @@ fn str_to_lower_lit(input: &[String]) -> Vec<LitStr> {
         .collect()
 }
 
+fn severity_to_ident(severity: SpecSeverity) -> Ident {
+    match severity {
+        SpecSeverity::Error => Ident::new("Error", Span::call_site()),
+        SpecSeverity::Warning => Ident::new("Warning", Span::call_site()),
+        SpecSeverity::Info => Ident::new("Info", Span::call_site()),
+    }
+}
+
 fn priority_to_ident(prio: SpecPriority) -> Ident {
     match prio {
         SpecPriority::Required => Ident::new("Required", Span::call_site()),
+        SpecPriority::Recommended => Ident::new("Recommended", Span::call_site()),
         SpecPriority::Optional => Ident::new("Optional", Span::call_site()),
     }
 }
 
-fn implement_attribute_spec(template: &SpecTemplate) -> Vec<TokenStream> {
+fn type_variant(kind: &SpecType) -> TokenStream {
+    match *kind {
+        SpecType::Text => quote! { Text },
+        SpecType::Integer => quote! { Integer },
+        SpecType::Boolean => quote! { Boolean },
+        SpecType::Enum(ref values) => {
+            let values = str_to_lower_lit(values);
+            quote! { Enum(vec![ #( #values.into() ),* ]) }
+        }
+        SpecType::Wikitext => quote! { Wikitext },
+        SpecType::Formula => quote! { Formula },
+        SpecType::File => quote! { File },
+    }
+}
+
+fn default_value(default: &SpecDefault) -> TokenStream {
+    match *default {
+        SpecDefault::Text(ref text) => quote! { DefaultValue::Text(#text.into()) },
+        SpecDefault::Wikitext(ref text) => quote! { DefaultValue::Wikitext(#text.into()) },
+    }
+}
+
+fn deprecation_value(deprecation: &SpecDeprecation) -> TokenStream {
+    let message = LitStr::new(&deprecation.message, Span::call_site());
+    let replacement = match deprecation.replacement {
+        Some(ref replacement) => quote! { Some(#replacement.into()) },
+        None => quote! { None },
+    };
+    quote! {
+        Deprecation {
+            message: #message.into(),
+            replacement: #replacement,
+        }
+    }
+}
+
+/// `deprecated` and `deprecated_names` of a template or attribute spec.
+fn deprecation_fields(
+    deprecated: &Option<SpecDeprecation>,
+    deprecated_names: &BTreeMap<String, SpecDeprecation>,
+) -> TokenStream {
+    let deprecated = match *deprecated {
+        Some(ref deprecation) => {
+            let value = deprecation_value(deprecation);
+            quote! { Some(#value) }
+        }
+        None => quote! { None },
+    };
+    let names = deprecated_names
+        .keys()
+        .map(|name| LitStr::new(&name.trim().to_lowercase(), Span::call_site()));
+    let values = deprecated_names.values().map(deprecation_value);
+    quote! {
+        deprecated: #deprecated,
+        deprecated_names: vec![ #( (#names.into(), #values) ),* ].into_iter().collect(),
+    }
+}
+
+/// Built-in predicates (`builtin::name`) are taken from `spec_meta::predicates`,
+/// all others are expected in scope of the `template_spec!` call.
+fn predicate_path(name: &str) -> TokenStream {
+    match name.strip_prefix(BUILTIN_PREFIX) {
+        Some(builtin) => {
+            let ident = Ident::new(builtin, Span::call_site());
+            quote! { spec_meta::predicates::#ident }
+        }
+        None => {
+            let ident = Ident::new(name, Span::call_site());
+            quote! { #ident }
+        }
+    }
+}
+
+/// Name of the generated function for a predicate expression with arguments.
+fn predicate_function(template_index: usize, attribute_index: usize) -> Ident {
+    let name = format!("predicate_{}_{}", template_index, attribute_index);
+    Ident::new(&name, Span::call_site())
+}
+
+/// An expression evaluating a predicate expression for `content`.
+fn predicate_call(expr: &PredicateExpr) -> TokenStream {
+    match *expr {
+        PredicateExpr::Name(ref name) => {
+            let path = predicate_path(name);
+            quote! { #path(content) }
+        }
+        PredicateExpr::Call(ref name, ref args) => match (name.as_str(), args.as_slice()) {
+            ("all", _) => {
+                let args = args.iter().map(predicate_call);
+                quote! { Ok(()) #( .and_then(|_| #args) )* }
+            }
+            ("any", _) => {
+                let first = predicate_call(&args[0]);
+                let rest = args[1..].iter().map(predicate_call);
+                quote! {
+                    #first #(
+                        .or_else(|first| #rest.map_err(|other| PredError {
+                            tree: first.tree,
+                            cause: format!("{} Or: {}", first.cause, other.cause),
+                        }))
+                    )*
+                }
+            }
+            ("not", [arg]) => {
+                let call = predicate_call(arg);
+                let cause =
+                    LitStr::new(&format!("{} must not apply here!", arg), Span::call_site());
+                quote! {
+                    match #call {
+                        Ok(()) => Err(PredError {
+                            tree: content.first(),
+                            cause: #cause.into(),
+                        }),
+                        Err(_) => Ok(()),
+                    }
+                }
+            }
+            ("max_length", [PredicateExpr::Integer(limit)]) => {
+                let limit = proc_macro2::Literal::u64_unsuffixed(*limit);
+                quote! {
+                    spec_meta::predicates::max_plain_text_length(content, #limit)
+                }
+            }
+            _ => unreachable!("predicate expressions are checked in load_spec"),
+        },
+        PredicateExpr::Integer(_) => unreachable!("predicate expressions are checked in load_spec"),
+    }
+}
+
+/// Functions for all predicate expressions with arguments,
+/// in a module private to the `template_spec!` call.
+fn implement_predicate_functions(templates: &[SpecTemplate]) -> TokenStream {
+    let mut functions = vec![];
+    for (template_index, template) in templates.iter().enumerate() {
+        for (attribute_index, attribute) in template.attributes.iter().enumerate() {
+            let expr = match parse_predicate(&attribute.predicate) {
+                Ok(expr @ PredicateExpr::Call(..)) => expr,
+                _ => continue,
+            };
+            let name = predicate_function(template_index, attribute_index);
+            let doc = format!(
+                " `{}` of attribute `{}` of template `{}`.",
+                expr, attribute.identifier, template.identifier
+            );
+            let call = predicate_call(&expr);
+            functions.push(quote! {
+                #[doc = #doc]
+                pub fn #name(content: &[Element]) -> PredResult {
+                    #call
+                }
+            });
+        }
+    }
+    if functions.is_empty() {
+        return quote! {};
+    }
+    quote! {
+        mod spec_predicates {
+            use super::*;
+
+            #( #functions )*
+        }
+    }
+}
+
+fn implement_attribute_spec(template_index: usize, template: &SpecTemplate) -> Vec<TokenStream> {
     template
         .attributes
         .iter()
-        .map(|attribute| {
+        .enumerate()
+        .map(|(attribute_index, attribute)| {
             let names = str_to_lower_lit(&attribute.names);
             let priority = priority_to_ident(attribute.priority);
-            let predicate = Ident::new(&attribute.predicate, Span::call_site());
+            let predicate = match parse_predicate(&attribute.predicate) {
+                Ok(PredicateExpr::Name(ref name)) => predicate_path(name),
+                _ => {
+                    let function = predicate_function(template_index, attribute_index);
+                    quote! { spec_predicates::#function }
+                }
+            };
             let description = LitStr::new(&attribute.description, Span::call_site());
             let pred_name = LitStr::new(&attribute.predicate, Span::call_site());
+            let severity = severity_to_ident(attribute.severity.unwrap_or(SpecSeverity::Error));
+            let (context_predicate, context_predicate_name) = match attribute.context_predicate {
+                Some(ref name) => {
+                    let ident = Ident::new(name, Span::call_site());
+                    (quote! { Some(&#ident) }, quote! { Some(#name.into()) })
+                }
+                None => (quote! { None }, quote! { None }),
+            };
+            let identifier = LitStr::new(&attribute.identifier, Span::call_site());
+            let repeat = attribute.repeat;
+            let position = match attribute.position {
+                Some(position) => quote! { Some(#position) },
+                None => quote! { None },
+            };
+            let kind = match attribute.kind {
+                Some(ref kind) => {
+                    let variant = type_variant(kind);
+                    quote! { Some(AttributeType::#variant) }
+                }
+                None => quote! { None },
+            };
+            let default = match attribute.default {
+                Some(ref default) => {
+                    let value = default_value(default);
+                    quote! { Some(#value) }
+                }
+                None => quote! { None },
+            };
+            let deprecation =
+                deprecation_fields(&attribute.deprecated, &attribute.deprecated_names);
+            let requires = &attribute.requires;
+            let conflicts_with = &attribute.conflicts_with;
             quote! {
                 AttributeSpec {
+                    identifier: #identifier.into(),
+                    #deprecation
+                    requires: vec![ #( #requires.into() ),* ],
+                    conflicts_with: vec![ #( #conflicts_with.into() ),* ],
+                    position: #position,
+                    repeat: #repeat,
+                    kind: #kind,
+                    default: #default,
                     names: vec![ #( #names.into() ),*],
                     priority: Priority::#priority,
                     predicate: &#predicate,
                     predicate_name: #pred_name.into(),
+                    context_predicate: #context_predicate,
+                    context_predicate_name: #context_predicate_name,
+                    severity: Severity::#severity,
                     description: #description.into(),
                 }
             }
@@ -163,12 +365,31 @@ fn implement_attribute_spec(template: &SpecTemplate) -> Vec<TokenStream> {
         .collect()
 }
 
+fn allowed_templates(allowed: &Option<Vec<String>>) -> TokenStream {
+    match *allowed {
+        Some(ref ids) => quote! { Some(vec![ #( #ids.into() ),* ]) },
+        None => quote! { None },
+    }
+}
+
 fn implement_spec_list(templates: &[SpecTemplate]) -> TokenStream {
-    let specs = templates.iter().map(|template| {
-        let (_, names, format, description) = check_template(template);
-        let attributes = implement_attribute_spec(template);
+    let specs = templates.iter().enumerate().map(|(index, template)| {
+        let (_, names, format, description) = template_tokens(template);
+        let attributes = implement_attribute_spec(index, template);
+        let identifier = LitStr::new(&template.identifier, Span::call_site());
+        let deprecation = deprecation_fields(&template.deprecated, &template.deprecated_names);
+        let one_of = template.one_of.iter().map(|group| {
+            quote! { vec![ #( #group.into() ),* ] }
+        });
+        let allowed_parents = allowed_templates(&template.allowed_parents);
+        let allowed_children = allowed_templates(&template.allowed_children);
         quote! {
             TemplateSpec {
+                identifier: #identifier.into(),
+                #deprecation
+                one_of: vec![ #( #one_of ),* ],
+                allowed_parents: #allowed_parents,
+                allowed_children: #allowed_children,
                 names: vec![ #( #names.into() ),* ],
                 description: #description.into(),
                 format: Format::#format,
@@ -176,7 +397,10 @@ fn implement_spec_list(templates: &[SpecTemplate]) -> TokenStream {
             }
         }
     });
+    let predicates = implement_predicate_functions(templates);
     quote! {
+        #predicates
+
         /// A representation of all templates in )the specification.
         pub fn spec<'p>() -> Vec<TemplateSpec<'p>> {
             vec![ #( #specs ),* ]
@@ -184,58 +408,107 @@ fn implement_spec_list(templates: &[SpecTemplate]) -> TokenStream {
     }
 }
 
+/// Arguments for `extract_argument` in `parse_template`: alternative names and position.
+fn attribute_lookup(attribute: &SpecAttribute) -> TokenStream {
+    let alt_names = str_to_lower_lit(&attribute.names);
+    let positional = attribute.position.map(|p| p.to_string()).into_iter();
+    quote! {
+        &[ #( #alt_names.into() ),* ], &[ #( #positional.into() ),* ]
+    }
+}
+
 fn implement_parsing_match(template: &SpecTemplate) -> TokenStream {
-    let (name, names, format, description) = check_template(template);
+    let (name, names, format, description) = template_tokens(template);
     let ident_str = LitStr::new(&template.identifier, Span::call_site());
     let attributes = template.attributes.iter().map(|attr| {
         let attr_name = Ident::new(&attr.identifier, Span::call_site());
-        let alt_names = str_to_lower_lit(&attr.names);
+        let lookup = attribute_lookup(attr);
+        if attr.repeat {
+            let prefixes = str_to_lower_lit(&attr.names);
+            return quote! {
+                #attr_name: extract_repeated(&[ #( #prefixes.into() ),* ])
+                    .into_iter()
+                    .map(|arg| arg.value.as_slice())
+                    .collect()
+            };
+        }
         match attr.priority {
+            // presence of required arguments is checked beforehand.
             SpecPriority::Required => quote! {
-                #attr_name: {
-                    // abort template parsing if required argument is missing.
-                    if let Some(c) = extract_content(&[ #( #alt_names.into() ),* ]) {
-                        c
-                    } else {
-                        return None
-                    }
-                }
+                #attr_name: extract_content(#lookup).unwrap_or_default()
             },
-            SpecPriority::Optional => quote! {
-                #attr_name: extract_content(&[ #( #alt_names.into() ),* ])
+            SpecPriority::Optional | SpecPriority::Recommended => quote! {
+                #attr_name: extract_content(#lookup)
             },
         }
     });
     let present = template.attributes.iter().map(|attr| {
-        let alt_names = str_to_lower_lit(&attr.names);
+        let lookup = attribute_lookup(attr);
         let att_name = LitStr::new(&attr.identifier, Span::call_site());
         let priority = priority_to_ident(attr.priority);
+        if attr.repeat {
+            let prefixes = str_to_lower_lit(&attr.names);
+            return quote! {
+                for arg in extract_repeated(&[ #( #prefixes.into() ),* ]) {
+                    present.push(Attribute {
+                        name: #att_name.into(),
+                        priority: Priority::#priority,
+                        value: &arg.value,
+                        position: arg.position.clone(),
+                    });
+                }
+            };
+        }
         quote! {
-            if let Some(value) = extract_content(&[ #( #alt_names.into() ),* ]) {
+            if let Some(arg) = extract_argument(#lookup) {
                 present.push(Attribute {
                     name: #att_name.into(),
                     priority: Priority::#priority,
-                    value,
+                    value: &arg.value,
+                    position: arg.position.clone(),
                 });
             }
         }
     });
+    let required = template
+        .attributes
+        .iter()
+        .filter(|attr| attr.priority == SpecPriority::Required)
+        .map(|attr| LitStr::new(&attr.identifier, Span::call_site()));
     quote! {
         let names = vec![#( #names.trim().to_lowercase() ),*];
         if names.contains(&name) {
+            let present = {
+                let mut present = vec![];
+                #( #present )*
+                present
+            };
+            let required: &[&str] = &[ #( #required ),* ];
+            let missing: Vec<String> = required
+                .iter()
+                .filter(|id| !present.iter().any(|attribute: &Attribute| &attribute.name == *id))
+                .map(|id| id.to_string())
+                .collect();
+            if !missing.is_empty() {
+                return Some(Err(IncompleteTemplate {
+                    identifier: #ident_str.into(),
+                    names,
+                    description: #description.into(),
+                    format: Format::#format,
+                    present,
+                    missing,
+                }));
+            }
             let template = #name {
                 identifier: #ident_str.into(),
-                names: names,
+                position: template.position.clone(),
+                names,
                 description: #description.into(),
                 format: Format::#format,
                 #( #attributes ),*,
-                present: {
-                    let mut present = vec![];
-                    #( #present )*
-                    present
-                }
+                present,
             };
-            return Some(KnownTemplate::#name(template));
+            return Some(Ok(KnownTemplate::#name(template)));
         }
     }
 }
@@ -246,14 +519,42 @@ fn implement_template_parsing(templates: &[SpecTemplate]) -> TokenStream {
     quote! {
         /// Try to create a `KnownTemplate` variant from an element, using the specification.
         pub fn parse_template<'e>(template: &'e Template) -> Option<KnownTemplate<'e>> {
-            let extract_content = | attr_names: &[String] | {
-                if let Some(arg) = find_arg(&template.content, attr_names) {
-                    if let Element::TemplateArgument(ref arg) = *arg {
-                        return Some(arg.value.as_slice())
-                    }
+            parse_template_partial(template).and_then(Result::ok)
+        }
+
+        /// Like `parse_template`, but a known template with missing required attributes
+        /// yields an `IncompleteTemplate` instead of `None`.
+        pub fn parse_template_partial<'e>(
+            template: &'e Template,
+        ) -> Option<Result<KnownTemplate<'e>, IncompleteTemplate<'e>>> {
+            // named arguments take precedence over positional ones.
+            let extract_argument = | attr_names: &[String], positional: &[String] | {
+                let arg = find_arg(&template.content, attr_names)
+                    .or_else(|| find_arg(&template.content, positional));
+                if let Some(Element::TemplateArgument(arg)) = arg {
+                    return Some(arg)
                 }
                 None
             };
+            let extract_content = | attr_names: &[String], positional: &[String] | {
+                extract_argument(attr_names, positional).map(|arg| arg.value.as_slice())
+            };
+            // arguments of repeated attributes, ordered by their number.
+            // Of duplicate numbers, the last one is used.
+            let extract_repeated = | prefixes: &[String] | {
+                let mut items: Vec<(usize, &'e mediawiki_parser::TemplateArgument)> = vec![];
+                for child in &template.content {
+                    if let Element::TemplateArgument(ref arg) = *child {
+                        let name = arg.name.trim().to_lowercase();
+                        if let Some(index) = prefixes.iter().filter_map(|p| repeat_index(&name, p)).next() {
+                            items.retain(|&(other, _)| other != index);
+                            items.push((index, arg));
+                        }
+                    }
+                }
+                items.sort_by_key(|&(index, _)| index);
+                items.into_iter().map(|(_, arg)| arg).collect::<Vec<_>>()
+            };
 
             let name = extract_plain_text(&template.name).trim().to_lowercase();
             #( #template_kinds )*
@@ -262,11 +563,540 @@ fn implement_template_parsing(templates: &[SpecTemplate]) -> TokenStream {
     }
 }
 
+fn implement_validation() -> TokenStream {
+    quote! {
+        /// Check the predicates of all attributes present in a template.
+        /// Uses of deprecated templates and attributes are reported as warnings.
+        pub fn validate_template(template: &KnownTemplate) -> Vec<Violation> {
+            validate_template_at(template, &[])
+        }
+
+        /// Like `validate_template`, with the ancestors of the template element
+        /// (starting at the document root) passed on to context predicates.
+        pub fn validate_template_at(template: &KnownTemplate, path: &[&Element]) -> Vec<Violation> {
+            match spec()
+                .into_iter()
+                .find(|s| s.identifier == template.identifier())
+            {
+                Some(template_spec) => {
+                    validate_attributes(&template_spec, template.present(), template.position(), path)
+                }
+                None => vec![],
+            }
+        }
+
+        /// Check the attributes present in a template and the constraints between them.
+        fn validate_attributes(
+            template_spec: &TemplateSpec,
+            present: &[Attribute],
+            position: &Span,
+            path: &[&Element],
+        ) -> Vec<Violation> {
+            let find = |identifier: &str| present.iter().find(|a| a.name == identifier);
+            let mut violations = vec![];
+            if let Some(ref deprecation) = template_spec.deprecated {
+                violations.push(Violation {
+                    template: template_spec.identifier.clone(),
+                    attribute: String::new(),
+                    predicate_name: "deprecated".into(),
+                    cause: deprecation.message.clone(),
+                    position: position.clone(),
+                    severity: Severity::Warning,
+                });
+            }
+            for attribute in present {
+                for attribute_spec in &template_spec.attributes {
+                    if attribute_spec.identifier != attribute.name {
+                        continue;
+                    }
+                    if let Some(ref deprecation) = attribute_spec.deprecated {
+                        violations.push(Violation {
+                            template: template_spec.identifier.clone(),
+                            attribute: attribute.name.clone(),
+                            predicate_name: "deprecated".into(),
+                            cause: deprecation.message.clone(),
+                            position: attribute.position.clone(),
+                            severity: Severity::Warning,
+                        });
+                    }
+                    let failure = |predicate_name: &str, error: PredError| Violation {
+                        template: template_spec.identifier.clone(),
+                        attribute: attribute.name.clone(),
+                        predicate_name: predicate_name.into(),
+                        cause: error.cause,
+                        position: error
+                            .tree
+                            .map(|tree| tree.get_position().clone())
+                            .unwrap_or_else(|| attribute.position.clone()),
+                        severity: attribute_spec.severity,
+                    };
+                    if let Err(error) = (attribute_spec.predicate)(attribute.value) {
+                        violations.push(failure(&attribute_spec.predicate_name, error));
+                    }
+                    if let (Some(predicate), Some(ref name)) =
+                        (attribute_spec.context_predicate, &attribute_spec.context_predicate_name)
+                    {
+                        let context = PredContext {
+                            template: &template_spec.identifier,
+                            attribute: &attribute.name,
+                            siblings: present,
+                            path,
+                            spec: template_spec,
+                        };
+                        if let Err(error) = (predicate)(attribute.value, &context) {
+                            violations.push(failure(name, error));
+                        }
+                    }
+                }
+            }
+            for attribute_spec in &template_spec.attributes {
+                if attribute_spec.priority == Priority::Recommended
+                    && find(&attribute_spec.identifier).is_none()
+                {
+                    violations.push(Violation {
+                        template: template_spec.identifier.clone(),
+                        attribute: attribute_spec.identifier.clone(),
+                        predicate_name: "recommended".into(),
+                        cause: format!("`{}` should be given!", attribute_spec.identifier),
+                        position: position.clone(),
+                        severity: Severity::Warning,
+                    });
+                }
+            }
+            violations.extend(check_constraints(template_spec, present, position));
+            violations
+        }
+
+        /// Check the `requires`, `conflicts_with` and `one_of` constraints of a template.
+        fn check_constraints(
+            template_spec: &TemplateSpec,
+            present: &[Attribute],
+            position: &Span,
+        ) -> Vec<Violation> {
+            let find = |identifier: &str| present.iter().find(|a| a.name == identifier);
+            let mut violations = vec![];
+            let violation = |attribute: &str, constraint: &str, cause: String, position: &Span| {
+                Violation {
+                    template: template_spec.identifier.clone(),
+                    attribute: attribute.into(),
+                    predicate_name: constraint.into(),
+                    cause,
+                    position: position.clone(),
+                    severity: Severity::Error,
+                }
+            };
+            let mut conflicts: Vec<(&str, &str)> = vec![];
+            for attribute_spec in &template_spec.attributes {
+                let attribute = match find(&attribute_spec.identifier) {
+                    Some(attribute) => attribute,
+                    None => continue,
+                };
+                for other in &attribute_spec.requires {
+                    if find(other).is_none() {
+                        violations.push(violation(
+                            &attribute.name,
+                            "requires",
+                            format!("`{}` requires `{}`, which is missing!", attribute.name, other),
+                            &attribute.position,
+                        ));
+                    }
+                }
+                for other in &attribute_spec.conflicts_with {
+                    let reported = conflicts.contains(&(other.as_str(), attribute.name.as_str()));
+                    if let (Some(other), false) = (find(other), reported) {
+                        violations.push(violation(
+                            &attribute.name,
+                            "conflicts_with",
+                            format!("`{}` cannot be given together with `{}`!", attribute.name, other.name),
+                            &attribute.position,
+                        ));
+                        conflicts.push((&attribute.name, &other.name));
+                    }
+                }
+            }
+            for group in &template_spec.one_of {
+                let given: Vec<&Attribute> = group.iter().filter_map(|id| find(id)).collect();
+                let names: Vec<String> = group.iter().map(|id| format!("`{}`", id)).collect();
+                match given.len() {
+                    0 => violations.push(violation(
+                        "",
+                        "one_of",
+                        format!("one of {} is required!", names.join(", ")),
+                        position,
+                    )),
+                    1 => (),
+                    _ => violations.push(violation(
+                        &given[1].name,
+                        "one_of",
+                        format!("only one of {} may be given!", names.join(", ")),
+                        &given[1].position,
+                    )),
+                }
+            }
+            violations
+        }
+
+        /// Parse a template element and check its attribute predicates.
+        /// In addition to `validate_template`, missing required attributes are reported
+        /// as errors and uses of deprecated names as warnings.
+        /// Returns `None` if the element is not a known template.
+        pub fn validate_raw_template(template: &Template) -> Option<Vec<Violation>> {
+            validate_raw_template_at(template, &[])
+        }
+
+        /// Like `validate_raw_template`, with the ancestors of the template element
+        /// (starting at the document root) passed on to context predicates.
+        pub fn validate_raw_template_at(template: &Template, path: &[&Element]) -> Option<Vec<Violation>> {
+            let (identifier, present, missing) = match parse_template_partial(template)? {
+                Ok(known) => (known.identifier().to_string(), known.present().clone(), vec![]),
+                Err(incomplete) => (incomplete.identifier, incomplete.present, incomplete.missing),
+            };
+            let template_spec = spec().into_iter().find(|s| s.identifier == identifier)?;
+            let mut violations = validate_attributes(&template_spec, &present, &template.position, path);
+            for attribute in missing {
+                violations.push(Violation {
+                    template: template_spec.identifier.clone(),
+                    cause: format!("`{}` is required!", attribute),
+                    attribute,
+                    predicate_name: "required".into(),
+                    position: template.position.clone(),
+                    severity: Severity::Error,
+                });
+            }
+
+            let name = extract_plain_text(&template.name).trim().to_lowercase();
+            if let Some(deprecation) = template_spec.deprecated_names.get(&name) {
+                violations.push(Violation {
+                    template: template_spec.identifier.clone(),
+                    attribute: String::new(),
+                    predicate_name: "deprecated".into(),
+                    cause: deprecation.message.clone(),
+                    position: template.position.clone(),
+                    severity: Severity::Warning,
+                });
+            }
+            for child in &template.content {
+                let arg = match *child {
+                    Element::TemplateArgument(ref arg) => arg,
+                    _ => continue,
+                };
+                let name = arg.name.trim().to_lowercase();
+                for attribute_spec in &template_spec.attributes {
+                    let deprecation = attribute_spec.deprecated_names.iter().find(|(alias, _)| {
+                        if attribute_spec.repeat {
+                            repeat_index(&name, alias).is_some()
+                        } else {
+                            **alias == name
+                        }
+                    });
+                    if let Some((_, deprecation)) = deprecation {
+                        violations.push(Violation {
+                            template: template_spec.identifier.clone(),
+                            attribute: attribute_spec.identifier.clone(),
+                            predicate_name: "deprecated".into(),
+                            cause: deprecation.message.clone(),
+                            position: arg.position.clone(),
+                            severity: Severity::Warning,
+                        });
+                    }
+                }
+            }
+            Some(violations)
+        }
+
+        /// Validates all templates of a document with their ancestors.
+        struct DocumentValidator<'e> {
+            pub path: Vec<&'e Element>,
+            pub result: Vec<Violation>,
+        }
+
+        impl<'e> mediawiki_parser::Traversion<'e, ()> for DocumentValidator<'e> {
+            fn path_push(&mut self, root: &'e Element) {
+                self.path.push(root);
+            }
+            fn path_pop(&mut self) -> Option<&'e Element> {
+                self.path.pop()
+            }
+            fn get_path(&self) -> &Vec<&'e Element> {
+                &self.path
+            }
+
+            fn work(
+                &mut self,
+                root: &'e Element,
+                _: (),
+                _: &mut std::io::Write
+            ) -> std::io::Result<bool> {
+                if let Element::Template(ref template) = *root {
+                    let ancestors = &self.path[..self.path.len() - 1];
+                    if let Some(violations) = validate_raw_template_at(template, ancestors) {
+                        self.result.extend(violations);
+                    }
+                }
+                Ok(true)
+            }
+        }
+
+        /// Validate all known templates of a document like `validate_raw_template_at`,
+        /// passing the ancestors of each template on to context predicates.
+        pub fn validate_document(root: &Element) -> Vec<Violation> {
+            let mut validator = DocumentValidator {
+                path: vec![],
+                result: vec![],
+            };
+            mediawiki_parser::Traversion::run(&mut validator, root, (), &mut vec![])
+                .expect("error validating document!");
+            validator.result
+        }
+    }
+}
+
+fn implement_argument_check() -> TokenStream {
+    quote! {
+        fn check_repeated(
+            attribute: &AttributeSpec,
+            args: Vec<(String, &Span)>,
+            issues: &mut Vec<ArgumentIssue>,
+        ) {
+            let mut numbered: Vec<(usize, String, &Span)> = vec![];
+            for (name, position) in args {
+                let index = attribute.names.iter().filter_map(|p| repeat_index(&name, p)).next();
+                match index {
+                    Some(index) => numbered.push((index, name, position)),
+                    None => issues.push(ArgumentIssue::InvalidRepeatSuffix {
+                        attribute: attribute.identifier.clone(),
+                        name,
+                        position: position.clone(),
+                    }),
+                }
+            }
+            numbered.sort_by_key(|&(index, _, _)| index);
+
+            let mut expected = 1;
+            let mut i = 0;
+            while i < numbered.len() {
+                let index = numbered[i].0;
+                if index > expected {
+                    issues.push(ArgumentIssue::RepeatGap {
+                        attribute: attribute.identifier.clone(),
+                        missing: (expected..index).collect(),
+                        position: numbered[i].2.clone(),
+                    });
+                }
+                let same: Vec<_> = numbered[i..].iter().take_while(|n| n.0 == index).collect();
+                if same.len() > 1 {
+                    issues.push(ArgumentIssue::Duplicate {
+                        attribute: attribute.identifier.clone(),
+                        name: same[0].1.clone(),
+                        positions: same.iter().map(|n| n.2.clone()).collect(),
+                    });
+                }
+                i += same.len();
+                expected = index + 1;
+            }
+        }
+
+        /// Report unknown, duplicate and conflicting arguments of a template.
+        /// Returns `None` if the element is not a known template.
+        pub fn check_arguments(template: &Template) -> Option<Vec<ArgumentIssue>> {
+            let template_spec = spec_of(&extract_plain_text(&template.name))?;
+            let mut issues = vec![];
+            let mut given: Vec<Vec<(String, &Span)>> = vec![vec![]; template_spec.attributes.len()];
+
+            for child in &template.content {
+                if let Element::TemplateArgument(ref arg) = *child {
+                    let name = arg.name.trim().to_lowercase();
+                    let index = template_spec
+                        .attributes
+                        .iter()
+                        .position(|attribute| attribute.names.contains(&name))
+                        .or_else(|| {
+                            template_spec.attributes.iter().position(|attribute| {
+                                attribute.position.map(|p| p.to_string()).as_ref() == Some(&name)
+                            })
+                        })
+                        .or_else(|| {
+                            template_spec.attributes.iter().position(|attribute| {
+                                attribute.repeat
+                                    && attribute.names.iter().any(|prefix| name.starts_with(prefix))
+                            })
+                        });
+                    match index {
+                        Some(index) => given[index].push((name, &arg.position)),
+                        None => issues.push(ArgumentIssue::Unknown {
+                            name: arg.name.trim().into(),
+                            position: arg.position.clone(),
+                        }),
+                    }
+                }
+            }
+
+            for (attribute, args) in template_spec.attributes.iter().zip(given) {
+                if attribute.repeat {
+                    check_repeated(attribute, args, &mut issues);
+                    continue;
+                }
+                if args.len() < 2 {
+                    continue;
+                }
+                let positions = args.iter().map(|(_, position)| (*position).clone()).collect();
+                let mut names: Vec<String> = vec![];
+                for (name, _) in args {
+                    if !names.contains(&name) {
+                        names.push(name);
+                    }
+                }
+                let positional = attribute.position.map(|p| p.to_string());
+                if names.len() > 1 && positional.map(|p| names.contains(&p)).unwrap_or(false) {
+                    issues.push(ArgumentIssue::NamedAndPositional {
+                        attribute: attribute.identifier.clone(),
+                        names,
+                        positions,
+                    });
+                } else if names.len() > 1 {
+                    issues.push(ArgumentIssue::ConflictingAliases {
+                        attribute: attribute.identifier.clone(),
+                        names,
+                        positions,
+                    });
+                } else {
+                    issues.push(ArgumentIssue::Duplicate {
+                        attribute: attribute.identifier.clone(),
+                        name: names.remove(0),
+                        positions,
+                    });
+                }
+            }
+            Some(issues)
+        }
+    }
+}
+
+/// Typed accessor method of an attribute with a specified type.
+fn implement_typed_accessor(attribute: &SpecAttribute) -> Option<TokenStream> {
+    let kind = attribute.kind.as_ref()?;
+    let field = Ident::new(&attribute.identifier, Span::call_site());
+    let method = Ident::new(
+        &format!("{}_value", attribute.identifier),
+        Span::call_site(),
+    );
+    let id_str = LitStr::new(&attribute.identifier, Span::call_site());
+    let (value_type, conversion) = match *kind {
+        SpecType::Text => (quote! { String }, quote! { convert_text(#id_str, content) }),
+        SpecType::Integer => (quote! { i64 }, quote! { convert_integer(#id_str, content) }),
+        SpecType::Boolean => (
+            quote! { bool },
+            quote! { convert_boolean(#id_str, content) },
+        ),
+        SpecType::Enum(ref values) => {
+            let values = str_to_lower_lit(values);
+            (
+                quote! { String },
+                quote! { convert_enum(#id_str, content, &[ #( #values.to_string() ),* ]) },
+            )
+        }
+        SpecType::Wikitext => (quote! { &'e [Element] }, quote! { Ok(content) }),
+        SpecType::Formula => (
+            quote! { &'e mediawiki_parser::Formatted },
+            quote! { convert_formula(#id_str, content) },
+        ),
+        SpecType::File => (quote! { String }, quote! { convert_file(#id_str, content) }),
+    };
+    if attribute.repeat {
+        let doc = format!(
+            "The values of `{}`, converted to their specified type.",
+            attribute.identifier
+        );
+        return Some(quote! {
+            #[doc = #doc]
+            pub fn #method(&self) -> Vec<Result<#value_type, ConversionError>> {
+                self.#field.iter().map(|&content| #conversion).collect()
+            }
+        });
+    }
+    let doc = format!(
+        "The value of `{}`, converted to its specified type.",
+        attribute.identifier
+    );
+    Some(match attribute.priority {
+        SpecPriority::Required => quote! {
+            #[doc = #doc]
+            pub fn #method(&self) -> Result<#value_type, ConversionError> {
+                let content = self.#field;
+                #conversion
+            }
+        },
+        SpecPriority::Optional | SpecPriority::Recommended => quote! {
+            #[doc = #doc]
+            pub fn #method(&self) -> Option<Result<#value_type, ConversionError>> {
+                self.#field.map(|content| #conversion)
+            }
+        },
+    })
+}
+
+/// Accessor method returning the given value or default of an optional attribute.
+fn implement_default_accessor(attribute: &SpecAttribute) -> Option<TokenStream> {
+    let default = attribute.default.as_ref()?;
+    let field = Ident::new(&attribute.identifier, Span::call_site());
+    let method = Ident::new(
+        &format!("{}_or_default", attribute.identifier),
+        Span::call_site(),
+    );
+    let value = default_value(default);
+    let doc = match *default {
+        SpecDefault::Text(ref text) | SpecDefault::Wikitext(ref text) => {
+            format!(
+                "The value of `{}`, or `{}` if not given.",
+                attribute.identifier, text
+            )
+        }
+    };
+    Some(quote! {
+        #[doc = #doc]
+        pub fn #method(&self) -> std::borrow::Cow<'e, [Element]> {
+            if let Some(content) = self.#field {
+                return std::borrow::Cow::Borrowed(content);
+            }
+            std::borrow::Cow::Owned(#value.to_elements())
+        }
+    })
+}
+
+/// Statements adding the arguments of an attribute in `to_template`.
+fn implement_argument_output(attribute: &SpecAttribute) -> TokenStream {
+    let attr_id = Ident::new(&attribute.identifier, Span::call_site());
+    let id_str = LitStr::new(&attribute.identifier, Span::call_site());
+    let name = str_to_lower_lit(&attribute.names).remove(0);
+    if attribute.repeat {
+        return quote! {
+            let attribute_positions = positions(#id_str);
+            for (index, value) in self.#attr_id.iter().enumerate() {
+                push_argument(
+                    format!("{}{}", #name, index + 1),
+                    value,
+                    attribute_positions.get(index).cloned(),
+                );
+            }
+        };
+    }
+    match attribute.priority {
+        SpecPriority::Required => quote! {
+            push_argument(#name.into(), self.#attr_id, positions(#id_str).first().cloned());
+        },
+        SpecPriority::Optional | SpecPriority::Recommended => quote! {
+            if let Some(value) = self.#attr_id {
+                push_argument(#name.into(), value, positions(#id_str).first().cloned());
+            }
+        },
+    }
+}
+
 fn implement_templates(templates: &[SpecTemplate]) -> Vec<TokenStream> {
     templates
         .iter()
         .map(|template| {
-            let (name, names, _, _) = check_template(template);
+            let (name, names, _, _) = template_tokens(template);
             let description = template
                 .description
                 .split('\n')
@@ -277,17 +1107,28 @@ fn implement_templates(templates: &[SpecTemplate]) -> Vec<TokenStream> {
                     .description
                     .split('\n')
                     .map(|l| LitStr::new(&l, Span::call_site()));
+                if attr.repeat {
+                    return quote! {
+                        #( #[doc = #description] )*
+                        pub #attr_id: Vec<&'e [Element]>
+                    };
+                }
                 match attr.priority {
                     SpecPriority::Required => quote! {
                         #( #[doc = #description] )*
                         pub #attr_id: &'e [Element]
                     },
-                    SpecPriority::Optional => quote! {
+                    SpecPriority::Optional | SpecPriority::Recommended => quote! {
                         #( #[doc = #description] )*
                         pub #attr_id: Option<&'e [Element]>
                     },
                 }
             });
+            let id_str = LitStr::new(&template.identifier, Span::call_site());
+            let accessors = template.attributes.iter().filter_map(implement_typed_accessor);
+            let defaults = template.attributes.iter().filter_map(implement_default_accessor);
+            let arguments = template.attributes.iter().map(implement_argument_output);
+            let default_name = str_to_lower_lit(&template.names).remove(0);
 
             quote! {
                 #[derive(Debug, Clone, PartialEq, Serialize)]
@@ -297,57 +1138,83 @@ fn implement_templates(templates: &[SpecTemplate]) -> Vec<TokenStream> {
                 #( #[doc = #names ] )*
                 pub struct #name<'e> {
                     pub identifier: String,
+                    pub position: Span,
                     pub names: Vec<String>,
                     pub format: Format,
                     pub description: String,
                     pub present: Vec<Attribute<'e>>,
                     # (#attribute_impls ),*
                 }
-            }
-        })
-        .collect()
-}
-
-fn read_file<P: AsRef<Path>>(path: P) -> io::Result<String> {
-    let mut file = fs::File::open(path.as_ref())?;
-    let mut string = String::new();
-    file.read_to_string(&mut string)?;
-    Ok(string)
-}
-
-#[proc_macro]
-pub fn template_spec(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let path_lit: LitStr = syn::parse(input.into()).expect("could not parse path string!");
-    let path = path_lit.value();
-
-    let root = env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".into());
-    let path = Path::new(&root).join(&path);
-    let file_name = match path.file_name() {
-        Some(file_name) => file_name,
-        None => panic!("spec attribute should point to a file"),
-    };
 
-    let data = match read_file(&path) {
-        Ok(data) => data,
-        Err(error) => panic!("error opening {:?}: {}", file_name, error),
-    };
-    let templates: Vec<SpecTemplate> = serde_yaml::from_str(&data).expect("cannot parse spec:");
+                impl<'e> #name<'e> {
+                    /// The TemplateData description of this template.
+                    pub fn template_data() -> TemplateData {
+                        spec()
+                            .into_iter()
+                            .find(|template| template.identifier == #id_str)
+                            .map(|template| template.template_data())
+                            .expect("template is part of the specification!")
+                    }
 
-    let template_id = implement_template_id(&templates);
-    let template_impls = implement_templates(&templates);
-    let spec_func = implement_spec_list(&templates);
-    let template_parsing = implement_template_parsing(&templates);
+                    /// Convert this template back into a template element.
+                    ///
+                    /// Attributes are given in the order of the specification, with their
+                    /// default names. Positions of the original template and arguments are kept.
+                    pub fn to_template(&self) -> Template {
+                        let mut content = vec![];
+                        {
+                            let mut push_argument = |name: String, value: &[Element], position: Option<&Span>| {
+                                content.push(Element::TemplateArgument(mediawiki_parser::TemplateArgument {
+                                    position: position.cloned().unwrap_or_else(Span::any),
+                                    name,
+                                    value: value.to_vec(),
+                                }));
+                            };
+                            let positions = |id: &str| {
+                                self.present.iter()
+                                    .filter(|attribute| attribute.name == id)
+                                    .map(|attribute| &attribute.position)
+                                    .collect::<Vec<_>>()
+                            };
+                            #( #arguments )*
+                        }
+                        Template {
+                            position: self.position.clone(),
+                            name: vec![Element::Text(mediawiki_parser::Text {
+                                position: Span::any(),
+                                text: #default_name.into(),
+                            })],
+                            content,
+                        }
+                    }
 
-    let implementation = quote! {
+                    /// Render this template as wikitext.
+                    pub fn to_wikitext(&self) -> String {
+                        to_wikitext(&[Element::Template(self.to_template())])
+                    }
 
-        use mediawiki_parser::{Element, Template};
-        use serde_derive::{Serialize};
+                    #( #accessors )*
+                    #( #defaults )*
+                }
+            }
+        })
+        .collect()
+}
 
+fn implement_spec_meta() -> TokenStream {
+    let conversions = implement_conversions();
+    let template_data = implement_template_data();
+    let help_page = implement_help_page();
+    let content_model = implement_content_model();
+    let combinators = implement_combinators();
+    let predicates = implement_predicates();
+    quote! {
         /// Types and utils used in the documentation.
         pub mod spec_meta {
 
+            use std::collections::BTreeMap;
             use std::io;
-            use mediawiki_parser::{Element, Traversion};
+            use mediawiki_parser::{Element, Formatted, MarkupType, Span, Text, Traversion};
             use serde_derive::{Serialize, Deserialize};
 
             /// Specifies wether a template represents a logical unit (`Block`)
@@ -363,10 +1230,83 @@ pub fn template_spec(input: proc_macro::TokenStream) -> proc_macro::TokenStream
             #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
             pub enum Priority {
                 Required,
+                /// Optional, but a warning is reported if missing.
+                Recommended,
                 Optional
             }
 
-            /// Represents failure of a predicate check.
+            /// The type of value an attribute is expected to contain.
+            #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+            pub enum AttributeType {
+                /// Text without any markup.
+                Text,
+                /// A (signed) integer number.
+                Integer,
+                /// `true` / `false`, `yes` / `no` or `ja` / `nein`.
+                Boolean,
+                /// One of a list of (lowercase) values.
+                Enum(Vec<String>),
+                /// Arbitrary markup.
+                Wikitext,
+                /// A single math formula.
+                Formula,
+                /// The name of a file, without namespace prefix.
+                File,
+            }
+
+            /// The value assumed for an optional attribute which is not given.
+            #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+            pub enum DefaultValue {
+                Text(String),
+                Wikitext(String),
+            }
+
+            impl DefaultValue {
+                /// Returns the default value as document elements.
+                pub fn to_elements(&self) -> Vec<Element> {
+                    match *self {
+                        DefaultValue::Text(ref text) => vec![Element::Text(Text {
+                            position: Span::any(),
+                            text: text.clone(),
+                        })],
+                        DefaultValue::Wikitext(ref source) => match mediawiki_parser::parse(source) {
+                            Ok(Element::Document(mut document)) => {
+                                // inline markup should not be wrapped in a paragraph.
+                                if document.content.len() == 1 {
+                                    if let Element::Paragraph(ref mut par) = document.content[0] {
+                                        return par.content.drain(..).collect();
+                                    }
+                                }
+                                document.content
+                            }
+                            Ok(other) => vec![other],
+                            Err(error) => vec![Element::Error(mediawiki_parser::Error {
+                                position: Span::any(),
+                                message: format!("invalid default value: {:?}", error),
+                            })],
+                        },
+                    }
+                }
+            }
+
+            /// Marks a template, attribute or name as deprecated.
+            #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+            pub struct Deprecation {
+                pub message: String,
+                /// Identifier of the template or attribute to use instead.
+                /// For names, the name to use instead of the default name.
+                pub replacement: Option<String>,
+            }
+
+            /// How serious a reported problem is.
+            #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+            pub enum Severity {
+                Error,
+                Warning,
+                Info,
+            }
+
+            /// Represents failure of a predicate check.
             pub struct PredError<'e> {
                 pub tree: Option<&'e Element>,
                 pub cause: String,
@@ -376,6 +1316,22 @@ pub fn template_spec(input: proc_macro::TokenStream) -> proc_macro::TokenStream
             pub type PredResult<'e> = Result<(), PredError<'e>>;
             /// A function to determine wether a given element is allowed.
             pub type Predicate = Fn(&[Element]) -> PredResult + Sync;
+            /// A predicate which also receives the context of the checked attribute.
+            pub type ContextPredicate = for<'e> Fn(&'e [Element], &PredContext) -> PredResult<'e> + Sync;
+
+            /// The attribute a `ContextPredicate` is applied to.
+            pub struct PredContext<'a> {
+                /// Identifier of the template.
+                pub template: &'a str,
+                /// Identifier of the attribute.
+                pub attribute: &'a str,
+                /// All attributes present in the template, including the checked one.
+                pub siblings: &'a [Attribute<'a>],
+                /// Ancestors of the template, starting at the document root.
+                /// Empty if the template was validated without its document.
+                pub path: &'a [&'a Element],
+                pub spec: &'a TemplateSpec<'a>,
+            }
 
             /// Checks a predicate for a given input tree.
             struct TreeChecker<'path, 'e> {
@@ -423,25 +1379,175 @@ pub fn template_spec(input: proc_macro::TokenStream) -> proc_macro::TokenStream
                 checker.result
             }
 
+            /// A predicate failure found by `collect_violations`.
+            #[derive(Debug, Clone, PartialEq, Serialize)]
+            pub struct PredViolation {
+                pub cause: String,
+                /// The offending element and its ancestors, starting below the checked root.
+                pub path: Vec<PathElement>,
+                pub position: Span,
+            }
+
+            /// Checks a predicate for a given input tree, collecting all failures.
+            struct ViolationCollector<'e> {
+                pub path: Vec<&'e Element>,
+                pub result: Vec<PredViolation>,
+            }
+
+            impl<'e, 'p, 's, 'q> Traversion<'e, (&'p Predicate, &'s [TemplateSpec<'q>])>
+                for ViolationCollector<'e>
+            {
+                fn path_push(&mut self, root: &'e Element) {
+                    self.path.push(root);
+                }
+                fn path_pop(&mut self) -> Option<&'e Element> {
+                    self.path.pop()
+                }
+                fn get_path(&self) -> &Vec<&'e Element> {
+                    &self.path
+                }
+
+                fn work_vec(
+                    &mut self,
+                    root: &'e [Element],
+                    settings: (&'p Predicate, &'s [TemplateSpec<'q>]),
+                    _: &mut io::Write
+                ) -> io::Result<bool> {
+                    let (predicate, specs) = settings;
+                    let error = match (predicate)(root) {
+                        Ok(()) => return Ok(true),
+                        Err(error) => error,
+                    };
+                    let mut path = self.path.clone();
+                    if let Some(tree) = error.tree {
+                        path.extend(path_to(root, tree));
+                    }
+                    let position = path
+                        .last()
+                        .map(|element| element.get_position().clone())
+                        .unwrap_or_default();
+                    // nested checks (e.g. with `always`) find the same failure on every level.
+                    let known = self.result
+                        .iter()
+                        .any(|v| v.position == position && v.cause == error.cause);
+                    if !known {
+                        self.result.push(PredViolation {
+                            cause: error.cause,
+                            path: path.iter().map(|element| PathElement::new(element, specs)).collect(),
+                            position,
+                        });
+                    }
+                    Ok(true)
+                }
+            }
+
+            /// Finds the path to an element in a tree.
+            struct PathFinder<'e> {
+                pub path: Vec<&'e Element>,
+                pub result: Vec<&'e Element>,
+            }
+
+            impl<'e, 't> Traversion<'e, &'t Element> for PathFinder<'e> {
+                fn path_push(&mut self, root: &'e Element) {
+                    self.path.push(root);
+                }
+                fn path_pop(&mut self) -> Option<&'e Element> {
+                    self.path.pop()
+                }
+                fn get_path(&self) -> &Vec<&'e Element> {
+                    &self.path
+                }
+
+                fn work(
+                    &mut self,
+                    root: &'e Element,
+                    target: &'t Element,
+                    _: &mut io::Write
+                ) -> io::Result<bool> {
+                    if self.result.is_empty() && std::ptr::eq(root, target) {
+                        self.result = self.path.clone();
+                    }
+                    Ok(true)
+                }
+            }
+
+            /// Elements from `root` down to `target`, empty if `target` is not in `root`.
+            fn path_to<'e>(root: &'e [Element], target: &Element) -> Vec<&'e Element> {
+                let mut finder = PathFinder {
+                    path: vec![],
+                    result: vec![],
+                };
+                finder.run_vec(&root, target, &mut vec![])
+                    .expect("error searching element!");
+                finder.result
+            }
+
+            /// Checks a predicate recursively like `always`, but continues after
+            /// failures and returns all of them. `specs` is used to name
+            /// known templates in the reported paths.
+            pub fn collect_violations(
+                root: &[Element],
+                predicate: &Predicate,
+                specs: &[TemplateSpec],
+            ) -> Vec<PredViolation> {
+                let mut collector = ViolationCollector {
+                    path: vec![],
+                    result: vec![],
+                };
+                collector.run_vec(&root, (predicate, specs), &mut vec![])
+                    .expect("error checking predicate!");
+                collector.result
+            }
+
 
             /// Represents a (semantic) template.
             #[derive(Clone, Serialize)]
             pub struct TemplateSpec<'p> {
+                pub identifier: String,
                 pub names: Vec<String>,
                 pub description: String,
                 pub format: Format,
                 pub attributes: Vec<AttributeSpec<'p>>,
+                pub deprecated: Option<Deprecation>,
+                /// Deprecated alternative names (lowercase).
+                pub deprecated_names: BTreeMap<String, Deprecation>,
+                /// Groups of attribute identifiers of which exactly one must be given.
+                pub one_of: Vec<Vec<String>>,
+                /// Identifiers of the templates this template may only be used in.
+                pub allowed_parents: Option<Vec<String>>,
+                /// Identifiers of the only templates which may be used in this template.
+                pub allowed_children: Option<Vec<String>>,
             }
 
             /// Represents the specification of an attribute (or argument) of a template.
             #[derive(Clone, Serialize)]
             pub struct AttributeSpec<'p> {
+                pub identifier: String,
                 pub names: Vec<String>,
                 pub description: String,
                 pub priority: Priority,
                 #[serde(skip)]
                 pub predicate: &'p Predicate,
                 pub predicate_name: String,
+                /// A predicate which also receives the context of the attribute.
+                #[serde(skip)]
+                pub context_predicate: Option<&'p ContextPredicate>,
+                pub context_predicate_name: Option<String>,
+                /// Severity of predicate failures.
+                pub severity: Severity,
+                pub kind: Option<AttributeType>,
+                pub default: Option<DefaultValue>,
+                /// Position of this attribute if given as unnamed argument.
+                pub position: Option<usize>,
+                /// Wether the names of this attribute are prefixes of numbered arguments.
+                pub repeat: bool,
+                pub deprecated: Option<Deprecation>,
+                /// Deprecated alternative names (lowercase).
+                pub deprecated_names: BTreeMap<String, Deprecation>,
+                /// Identifiers of attributes which must be given if this attribute is.
+                pub requires: Vec<String>,
+                /// Identifiers of attributes which must not be given together with this attribute.
+                pub conflicts_with: Vec<String>,
             }
 
             impl<'p> TemplateSpec<'p> {
@@ -460,14 +1566,1399 @@ pub fn template_spec(input: proc_macro::TokenStream) -> proc_macro::TokenStream
                 }
             }
 
+            #template_data
+
+            #help_page
+
+            #content_model
+
+            #combinators
+
+            #predicates
+
             /// Represents a concrete value of a template attribute.
             #[derive(Debug, Clone, PartialEq, Serialize)]
             pub struct Attribute<'e> {
                 pub name: String,
                 pub priority: Priority,
                 pub value: &'e [Element],
+                pub position: Span,
+            }
+
+            /// A known template which lacks some of its required attributes.
+            #[derive(Debug, Clone, PartialEq, Serialize)]
+            pub struct IncompleteTemplate<'e> {
+                pub identifier: String,
+                pub names: Vec<String>,
+                pub format: Format,
+                pub description: String,
+                pub present: Vec<Attribute<'e>>,
+                /// Identifiers of the missing required attributes.
+                pub missing: Vec<String>,
+            }
+
+            /// A failed predicate check of a template attribute, a violated
+            /// attribute constraint or the use of a deprecated template, attribute or name.
+            ///
+            /// `attribute` is empty for problems of the template itself. `predicate_name`
+            /// is `requires`, `conflicts_with` or `one_of` for constraints, `required` and
+            /// `recommended` for missing required and recommended attributes and
+            /// `deprecated` for deprecations.
+            #[derive(Debug, Clone, PartialEq, Serialize)]
+            pub struct Violation {
+                pub template: String,
+                pub attribute: String,
+                pub predicate_name: String,
+                pub cause: String,
+                pub position: Span,
+                pub severity: Severity,
+            }
+
+            /// Failure to convert an attribute value to its specified type.
+            #[derive(Debug, Clone, PartialEq, Serialize)]
+            pub struct ConversionError {
+                pub attribute: String,
+                pub cause: String,
+                pub position: Span,
+            }
+
+            #conversions
+
+            /// Returns the number of a repeated argument name (`item3` -> 3) for a prefix.
+            pub fn repeat_index(name: &str, prefix: &str) -> Option<usize> {
+                if !name.starts_with(prefix) {
+                    return None;
+                }
+                let suffix = &name[prefix.len()..];
+                if suffix.is_empty() || !suffix.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                suffix.parse().ok()
+            }
+
+            /// A problem with the arguments given to a template.
+            #[derive(Debug, Clone, PartialEq, Serialize)]
+            pub enum ArgumentIssue {
+                /// An argument name which is not part of the specification.
+                Unknown {
+                    name: String,
+                    position: Span,
+                },
+                /// An attribute given multiple times with the same name.
+                /// MediaWiki only uses the last occurrence.
+                Duplicate {
+                    attribute: String,
+                    name: String,
+                    positions: Vec<Span>,
+                },
+                /// An attribute given with multiple of its alternative names.
+                ConflictingAliases {
+                    attribute: String,
+                    names: Vec<String>,
+                    positions: Vec<Span>,
+                },
+                /// An argument of a repeated attribute without a valid number.
+                InvalidRepeatSuffix {
+                    attribute: String,
+                    name: String,
+                    position: Span,
+                },
+                /// Numbers missing from the arguments of a repeated attribute.
+                /// `position` is the one of the next given argument.
+                RepeatGap {
+                    attribute: String,
+                    missing: Vec<usize>,
+                    position: Span,
+                },
+                /// An attribute given both as named and as positional argument.
+                NamedAndPositional {
+                    attribute: String,
+                    names: Vec<String>,
+                    positions: Vec<Span>,
+                },
+            }
+        }
+    }
+}
+
+fn implement_template_data() -> TokenStream {
+    quote! {
+        /// Description of a template in the format of MediaWiki's TemplateData extension.
+        #[derive(Debug, Clone, PartialEq, Serialize)]
+        pub struct TemplateData {
+            pub description: String,
+            pub params: BTreeMap<String, TemplateDataParam>,
+            #[serde(rename = "paramOrder")]
+            pub param_order: Vec<String>,
+            pub format: String,
+        }
+
+        /// Description of a template parameter in TemplateData.
+        #[derive(Debug, Clone, PartialEq, Serialize)]
+        pub struct TemplateDataParam {
+            pub label: String,
+            pub description: String,
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            pub aliases: Vec<String>,
+            pub required: bool,
+            pub suggested: bool,
+            #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+            pub kind: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub default: Option<String>,
+            #[serde(rename = "suggestedvalues", skip_serializing_if = "Vec::is_empty")]
+            pub suggested_values: Vec<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub deprecated: Option<String>,
+        }
+
+        impl<'p> TemplateSpec<'p> {
+            /// Describe this template in the TemplateData format.
+            ///
+            /// Repeated attributes are represented by their first argument (e.g. `item1`).
+            pub fn template_data(&self) -> TemplateData {
+                let mut params = BTreeMap::new();
+                let mut param_order = vec![];
+                for attribute in &self.attributes {
+                    let mut aliases: Vec<String> = attribute.names.iter().skip(1).cloned().collect();
+                    let key = if attribute.repeat {
+                        aliases = aliases.iter().map(|alias| format!("{}1", alias)).collect();
+                        format!("{}1", attribute.default_name())
+                    } else {
+                        aliases.extend(attribute.position.map(|p| p.to_string()));
+                        attribute.default_name().to_string()
+                    };
+                    let kind = attribute.kind.as_ref().map(|kind| match *kind {
+                        AttributeType::Text | AttributeType::Enum(_) => "line",
+                        AttributeType::Integer => "number",
+                        AttributeType::Boolean => "boolean",
+                        AttributeType::Wikitext => "content",
+                        AttributeType::Formula => "string",
+                        AttributeType::File => "wiki-file-name",
+                    }.to_string());
+                    let suggested_values = match attribute.kind {
+                        Some(AttributeType::Enum(ref values)) => values.clone(),
+                        _ => vec![],
+                    };
+                    let default = match attribute.default {
+                        Some(DefaultValue::Text(ref text)) | Some(DefaultValue::Wikitext(ref text)) => {
+                            Some(text.clone())
+                        }
+                        None => None,
+                    };
+                    params.insert(key.clone(), TemplateDataParam {
+                        label: attribute.identifier.clone(),
+                        description: attribute.description.clone(),
+                        aliases,
+                        required: attribute.priority == Priority::Required,
+                        suggested: attribute.priority == Priority::Recommended,
+                        kind,
+                        default,
+                        suggested_values,
+                        deprecated: attribute.deprecated.as_ref().map(|d| d.message.clone()),
+                    });
+                    param_order.push(key);
+                }
+                TemplateData {
+                    description: self.description.clone(),
+                    params,
+                    param_order,
+                    format: match self.format {
+                        Format::Inline => "inline".into(),
+                        Format::Block | Format::Box => "block".into(),
+                    },
+                }
+            }
+        }
+    }
+}
+
+fn implement_combinators() -> TokenStream {
+    quote! {
+        /// A predicate used inside a combinator.
+        type SubPredicate<'p> = Fn(&[Element]) -> PredResult + Sync + 'p;
+
+        /// Finds the first element to which a predicate applies.
+        struct MatchFinder<'e> {
+            pub path: Vec<&'e Element>,
+            pub result: Option<&'e Element>,
+        }
+
+        impl<'e, 'p> Traversion<'e, &'p SubPredicate<'p>> for MatchFinder<'e> {
+            fn path_push(&mut self, root: &'e Element) {
+                self.path.push(root);
+            }
+            fn path_pop(&mut self) -> Option<&'e Element> {
+                self.path.pop()
+            }
+            fn get_path(&self) -> &Vec<&'e Element> {
+                &self.path
+            }
+
+            fn work(
+                &mut self,
+                root: &'e Element,
+                predicate: &'p SubPredicate<'p>,
+                _: &mut io::Write
+            ) -> io::Result<bool> {
+                if self.result.is_none() && predicate(std::slice::from_ref(root)).is_ok() {
+                    self.result = Some(root);
+                }
+                Ok(true)
+            }
+        }
+
+        /// Accepts content which satisfies both predicates.
+        /// Fails with the cause of the first failing predicate.
+        pub fn and<A, B>(first: A, second: B) -> Box<Predicate>
+        where
+            A: Fn(&[Element]) -> PredResult + Sync + 'static,
+            B: Fn(&[Element]) -> PredResult + Sync + 'static,
+        {
+            Box::new(move |content: &[Element]| {
+                first(content)?;
+                second(content)
+            })
+        }
+
+        /// Accepts content which satisfies at least one of the predicates.
+        /// Fails with the causes of both predicates.
+        pub fn or<A, B>(first: A, second: B) -> Box<Predicate>
+        where
+            A: Fn(&[Element]) -> PredResult + Sync + 'static,
+            B: Fn(&[Element]) -> PredResult + Sync + 'static,
+        {
+            Box::new(move |content: &[Element]| {
+                first(content).or_else(|first| second(content).map_err(|other| PredError {
+                    tree: first.tree,
+                    cause: format!("{} Or: {}", first.cause, other.cause),
+                }))
+            })
+        }
+
+        /// Accepts content which does not satisfy the predicate.
+        /// `cause` describes the failure, since the predicate itself gives none.
+        pub fn not<P>(predicate: P, cause: &str) -> Box<Predicate>
+        where
+            P: Fn(&[Element]) -> PredResult + Sync + 'static,
+        {
+            let cause = cause.to_string();
+            Box::new(move |content: &[Element]| match predicate(content) {
+                Ok(()) => Err(PredError {
+                    tree: content.first(),
+                    cause: cause.clone(),
+                }),
+                Err(_) => Ok(()),
+            })
+        }
+
+        /// Accepts content in which the predicate applies to no single element,
+        /// including all descendants. Fails at the first matching element,
+        /// `cause` describes the failure.
+        pub fn never<P>(predicate: P, cause: &str) -> Box<Predicate>
+        where
+            P: Fn(&[Element]) -> PredResult + Sync + 'static,
+        {
+            let cause = cause.to_string();
+            Box::new(move |content: &[Element]| {
+                let mut finder = MatchFinder {
+                    path: vec![],
+                    result: None,
+                };
+                finder.run_vec(&content, &predicate as &SubPredicate, &mut vec![])
+                    .expect("error checking predicate!");
+                match finder.result {
+                    Some(element) => Err(PredError {
+                        tree: Some(element),
+                        cause: cause.clone(),
+                    }),
+                    None => Ok(()),
+                }
+            })
+        }
+
+        /// Accepts content in which the predicate applies to at least one single element,
+        /// including all descendants. `cause` describes the failure.
+        pub fn exists<P>(predicate: P, cause: &str) -> Box<Predicate>
+        where
+            P: Fn(&[Element]) -> PredResult + Sync + 'static,
+        {
+            let cause = cause.to_string();
+            let check = never(predicate, "");
+            Box::new(move |content: &[Element]| match check(content) {
+                Ok(()) => Err(PredError {
+                    tree: content.first(),
+                    cause: cause.clone(),
+                }),
+                Err(_) => Ok(()),
+            })
+        }
+
+        /// Applies the predicate to each top-level element of the content on its own,
+        /// without descending into their children.
+        pub fn children_only<P>(predicate: P) -> Box<Predicate>
+        where
+            P: Fn(&[Element]) -> PredResult + Sync + 'static,
+        {
+            Box::new(move |content: &[Element]| {
+                content
+                    .iter()
+                    .try_for_each(|child| predicate(std::slice::from_ref(child)))
+            })
+        }
+
+        /// Accepts content of which all elements are one of the given kinds,
+        /// the variant names of `Element` (e.g. `Paragraph`).
+        pub fn element_kind_is(kinds: &[&str]) -> Box<Predicate> {
+            let kinds: Vec<String> = kinds.iter().map(|kind| kind.to_string()).collect();
+            Box::new(move |content: &[Element]| {
+                let other = content
+                    .iter()
+                    .find(|element| !kinds.iter().any(|kind| kind == element.get_variant_name()));
+                match other {
+                    Some(element) => Err(PredError {
+                        tree: Some(element),
+                        cause: format!(
+                            "expected {}, found {}!",
+                            kinds.join(" or "),
+                            element.get_variant_name()
+                        ),
+                    }),
+                    None => Ok(()),
+                }
+            })
+        }
+    }
+}
+
+fn implement_predicates() -> TokenStream {
+    quote! {
+        /// Predicates which can be used by name in every specification.
+        pub mod predicates {
+            use mediawiki_parser::{Element, MarkupType};
+            use super::{always, plain_text, PredError, PredResult, Predicate, FILE_NAMESPACES};
+            // expected in scope of the `template_spec!` call, like `find_arg`.
+            use super::super::extract_plain_text;
+
+            /// Names (`builtin::name`) and functions of all predicates in this module,
+            /// e.g. for registering them with a `SpecRegistry`.
+            pub fn builtins() -> Vec<(&'static str, &'static Predicate)> {
+                vec![
+                    ("builtin::plain_text_only", &plain_text_only),
+                    ("builtin::no_templates", &no_templates),
+                    ("builtin::no_block_elements", &no_block_elements),
+                    ("builtin::only_formulas", &only_formulas),
+                    ("builtin::single_paragraph", &single_paragraph),
+                    ("builtin::non_empty", &non_empty),
+                    ("builtin::no_headings", &no_headings),
+                    ("builtin::only_file_links", &only_file_links),
+                ]
+            }
+
+            fn fail<'e>(tree: Option<&'e Element>, cause: &str) -> PredResult<'e> {
+                Err(PredError {
+                    tree,
+                    cause: cause.into(),
+                })
+            }
+
+            fn is_block(element: &Element) -> bool {
+                match *element {
+                    Element::Heading(_) | Element::List(_) | Element::Table(_) | Element::Gallery(_) => true,
+                    Element::Formatted(ref e) => {
+                        e.markup == MarkupType::Blockquote || e.markup == MarkupType::Preformatted
+                    }
+                    _ => false,
+                }
+            }
+
+            fn is_whitespace(element: &Element) -> bool {
+                match *element {
+                    Element::Text(ref e) => e.text.trim().is_empty(),
+                    Element::Comment(_) => true,
+                    _ => false,
+                }
+            }
+
+            /// The first element of the second paragraph, if any.
+            /// The last paragraph of a text is not wrapped in a `Paragraph` element.
+            fn after_paragraph_break(content: &[Element]) -> Option<&Element> {
+                let first = content.iter().position(|e| matches!(e, Element::Paragraph(_)))?;
+                content[first + 1..].iter().find(|e| !is_whitespace(e))
+            }
+
+            /// Accepts text without any markup.
+            pub fn plain_text_only(content: &[Element]) -> PredResult {
+                for element in content {
+                    if plain_text("", std::slice::from_ref(element)).is_err() {
+                        return fail(Some(element), "only plain text is allowed here!");
+                    }
+                }
+                Ok(())
+            }
+
+            fn template_found(content: &[Element]) -> PredResult {
+                match content.iter().find(|e| matches!(e, Element::Template(_))) {
+                    Some(template) => fail(Some(template), "templates are not allowed here!"),
+                    None => Ok(()),
+                }
+            }
+
+            /// Accepts content without any (nested) templates.
+            pub fn no_templates(content: &[Element]) -> PredResult {
+                always(content, &template_found)
+            }
+
+            fn block_found(content: &[Element]) -> PredResult {
+                if let Some(block) = content.iter().find(|e| is_block(e)) {
+                    return fail(Some(block), "block elements are not allowed here!");
+                }
+                match after_paragraph_break(content) {
+                    Some(second) => fail(Some(second), "paragraph breaks are not allowed here!"),
+                    None => Ok(()),
+                }
+            }
+
+            /// Accepts inline content: no headings, lists, tables, galleries,
+            /// block quotes, preformatted text or paragraph breaks.
+            pub fn no_block_elements(content: &[Element]) -> PredResult {
+                always(content, &block_found)
+            }
+
+            /// Accepts content consisting only of math formulas.
+            pub fn only_formulas(content: &[Element]) -> PredResult {
+                for element in content {
+                    match *element {
+                        Element::Formatted(ref e) if e.markup == MarkupType::Math => (),
+                        Element::Paragraph(ref e) => only_formulas(&e.content)?,
+                        _ if is_whitespace(element) => (),
+                        _ => return fail(Some(element), "only formulas are allowed here!"),
+                    }
+                }
+                Ok(())
+            }
+
+            /// Accepts content which is at most one paragraph.
+            pub fn single_paragraph(content: &[Element]) -> PredResult {
+                if let Some(block) = content.iter().find(|e| is_block(e)) {
+                    return fail(Some(block), "only a single paragraph is allowed here!");
+                }
+                match after_paragraph_break(content) {
+                    Some(second) => fail(Some(second), "only a single paragraph is allowed here!"),
+                    None => Ok(()),
+                }
+            }
+
+            /// Accepts content which is not only whitespace or comments.
+            pub fn non_empty(content: &[Element]) -> PredResult {
+                let empty = content.iter().all(|element| match *element {
+                    Element::Paragraph(ref e) => non_empty(&e.content).is_err(),
+                    _ => is_whitespace(element),
+                });
+                if empty {
+                    return fail(content.first(), "content must not be empty!");
+                }
+                Ok(())
+            }
+
+            /// Accepts content with a plain text of at most `limit` characters.
+            pub fn max_plain_text_length(content: &[Element], limit: usize) -> PredResult {
+                let length = extract_plain_text(content).trim().chars().count();
+                if length > limit {
+                    return Err(PredError {
+                        tree: content.first(),
+                        cause: format!(
+                            "text must not be longer than {} characters, found {}!",
+                            limit, length
+                        ),
+                    });
+                }
+                Ok(())
+            }
+
+            fn heading_found(content: &[Element]) -> PredResult {
+                match content.iter().find(|e| matches!(e, Element::Heading(_))) {
+                    Some(heading) => fail(Some(heading), "headings are not allowed here!"),
+                    None => Ok(()),
+                }
+            }
+
+            /// Accepts content without any (nested) headings.
+            pub fn no_headings(content: &[Element]) -> PredResult {
+                always(content, &heading_found)
+            }
+
+            fn other_link_found(content: &[Element]) -> PredResult {
+                for element in content {
+                    match *element {
+                        Element::InternalReference(ref e) => {
+                            let target = plain_text("", &e.target).unwrap_or_default();
+                            let namespace = target.split(':').next().unwrap_or_default();
+                            let is_file = target.contains(':')
+                                && FILE_NAMESPACES.contains(&namespace.trim().to_lowercase().as_str());
+                            if !is_file {
+                                return fail(Some(element), "only links to files are allowed here!");
+                            }
+                        }
+                        Element::ExternalReference(_) => {
+                            return fail(Some(element), "only links to files are allowed here!");
+                        }
+                        _ => (),
+                    }
+                }
+                Ok(())
+            }
+
+            /// Accepts content in which all (nested) links are links to files.
+            pub fn only_file_links(content: &[Element]) -> PredResult {
+                always(content, &other_link_found)
+            }
+        }
+    }
+}
+
+fn implement_content_model() -> TokenStream {
+    quote! {
+        /// An element on the path from the document root, for error reports.
+        #[derive(Debug, Clone, PartialEq, Serialize)]
+        pub struct PathElement {
+            /// Variant name of the element (e.g. `ListItem`).
+            pub kind: String,
+            /// Identifier of a known template.
+            pub template: Option<String>,
+            pub position: Span,
+        }
+
+        impl PathElement {
+            pub fn new(element: &Element, specs: &[TemplateSpec]) -> PathElement {
+                PathElement {
+                    kind: element.get_variant_name().into(),
+                    template: find_spec(specs, element).map(|spec| spec.identifier.clone()),
+                    position: element.get_position().clone(),
+                }
+            }
+        }
+
+        /// A template used in a place not allowed by its format or specification.
+        #[derive(Debug, Clone, PartialEq, Serialize)]
+        pub struct Misplacement {
+            pub template: String,
+            pub cause: String,
+            pub position: Span,
+            /// Ancestors of the template, starting at the root.
+            pub path: Vec<PathElement>,
+        }
+
+        /// The specification of a template element, if it is known.
+        fn find_spec<'s, 'p>(specs: &'s [TemplateSpec<'p>], element: &Element) -> Option<&'s TemplateSpec<'p>> {
+            if let Element::Template(ref template) = *element {
+                let name = plain_text("", &template.name).ok()?.trim().to_lowercase();
+                return specs.iter().find(|spec| spec.names.contains(&name));
+            }
+            None
+        }
+
+        /// Describes an ancestor which cannot contain block or box templates.
+        fn inline_context(ancestor: &Element, specs: &[TemplateSpec]) -> Option<String> {
+            match *ancestor {
+                Element::ListItem(_) => Some("a list item".into()),
+                Element::Formatted(_) => Some("formatted text".into()),
+                Element::InternalReference(_) | Element::ExternalReference(_) => {
+                    Some("a link".into())
+                }
+                Element::Template(_) => find_spec(specs, ancestor)
+                    .filter(|spec| spec.format == Format::Inline)
+                    .map(|spec| format!("the inline template `{}`", spec.identifier)),
+                _ => None,
+            }
+        }
+
+        /// Checks the placement of known templates.
+        struct ContentModelChecker<'e> {
+            pub path: Vec<&'e Element>,
+            pub result: Vec<Misplacement>,
+        }
+
+        impl<'e, 's, 'p> Traversion<'e, &'s [TemplateSpec<'p>]> for ContentModelChecker<'e> {
+
+            fn path_push(&mut self, root: &'e Element) {
+                self.path.push(root);
+            }
+            fn path_pop(&mut self) -> Option<&'e Element> {
+                self.path.pop()
+            }
+            fn get_path(&self) -> &Vec<&'e Element> {
+                &self.path
+            }
+
+            fn work(
+                &mut self,
+                root: &'e Element,
+                specs: &'s [TemplateSpec<'p>],
+                _: &mut io::Write
+            ) -> io::Result<bool> {
+                let spec = match find_spec(specs, root) {
+                    Some(spec) => spec,
+                    None => return Ok(true),
+                };
+                let ancestors = &self.path[..self.path.len() - 1];
+                let mut causes = vec![];
+
+                if spec.format != Format::Inline {
+                    let context = ancestors.iter().rev().filter_map(|a| inline_context(a, specs)).next();
+                    if let Some(context) = context {
+                        causes.push(format!(
+                            "the {:?} template `{}` cannot be placed in {}!",
+                            spec.format, spec.identifier, context
+                        ));
+                    }
+                }
+
+                let parent = ancestors.iter().rev().filter_map(|a| find_spec(specs, a)).next();
+                if let Some(ref allowed) = spec.allowed_parents {
+                    if !parent.map(|p| allowed.contains(&p.identifier)).unwrap_or(false) {
+                        causes.push(format!(
+                            "`{}` can only be placed in {}!",
+                            spec.identifier,
+                            allowed.iter().map(|id| format!("`{}`", id)).collect::<Vec<_>>().join(", ")
+                        ));
+                    }
+                }
+                if let Some(parent) = parent {
+                    let forbidden = parent.allowed_children
+                        .as_ref()
+                        .map(|allowed| !allowed.contains(&spec.identifier))
+                        .unwrap_or(false);
+                    if forbidden {
+                        causes.push(format!(
+                            "`{}` cannot be placed in `{}`!",
+                            spec.identifier, parent.identifier
+                        ));
+                    }
+                }
+
+                for cause in causes {
+                    self.result.push(Misplacement {
+                        template: spec.identifier.clone(),
+                        cause,
+                        position: root.get_position().clone(),
+                        path: ancestors.iter().map(|a| PathElement::new(a, specs)).collect(),
+                    });
+                }
+                Ok(true)
+            }
+        }
+
+        /// Check the placement of all known templates in a document against their format
+        /// and their allowed parents and children.
+        pub fn check_content_model(root: &Element, specs: &[TemplateSpec]) -> Vec<Misplacement> {
+            let mut checker = ContentModelChecker {
+                path: vec![],
+                result: vec![],
+            };
+            checker.run(root, specs, &mut vec![])
+                .expect("error checking content model!");
+            checker.result
+        }
+    }
+}
+
+fn implement_help_page() -> TokenStream {
+    quote! {
+        /// Markup language of a generated help page.
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub enum HelpFormat {
+            Wikitext,
+            Markdown,
+        }
+
+        impl HelpFormat {
+            fn heading(self, level: usize, title: &str) -> String {
+                match self {
+                    HelpFormat::Wikitext => {
+                        let marker = "=".repeat(level);
+                        format!("{} {} {}\n", marker, title, marker)
+                    }
+                    HelpFormat::Markdown => format!("{} {}\n", "#".repeat(level - 1), title),
+                }
+            }
+
+            fn code(self, text: &str) -> String {
+                match self {
+                    HelpFormat::Wikitext => format!("<code><nowiki>{}</nowiki></code>", text),
+                    HelpFormat::Markdown => format!("`{}`", text),
+                }
+            }
+
+            fn cell(self, text: &str) -> String {
+                let text = text.trim().replace('\n', " ");
+                match self {
+                    HelpFormat::Wikitext => text.replace('|', "{{!}}"),
+                    HelpFormat::Markdown => text.replace('|', "\\|"),
+                }
+            }
+        }
+
+        impl<'p> TemplateSpec<'p> {
+            /// A copy-pasteable call of this template with all of its attributes,
+            /// except for deprecated ones.
+            ///
+            /// Repeated attributes are shown with their first argument (e.g. `item1`).
+            pub fn usage(&self) -> String {
+                let attributes = self.attributes.iter().filter(|a| a.deprecated.is_none());
+                let arguments: Vec<String> = attributes.map(|attribute| {
+                    if attribute.repeat {
+                        format!("{}1", attribute.default_name())
+                    } else {
+                        attribute.default_name().to_string()
+                    }
+                }).collect();
+                match self.format {
+                    Format::Inline => {
+                        let mut result = format!("{{{{{}", self.default_name());
+                        for argument in &arguments {
+                            result.push_str(&format!("|{}=", argument));
+                        }
+                        result.push_str("}}");
+                        result
+                    }
+                    Format::Block | Format::Box => {
+                        let mut result = format!("{{{{{}\n", self.default_name());
+                        for argument in &arguments {
+                            result.push_str(&format!("| {} = \n", argument));
+                        }
+                        result.push_str("}}");
+                        result
+                    }
+                }
+            }
+
+            /// Render the documentation of this template as help page section.
+            pub fn help_section(&self, format: HelpFormat) -> String {
+                let mut result = format.heading(2, self.default_name());
+                result.push('\n');
+                result.push_str(self.description.trim());
+                result.push_str("\n\n");
+                result.push_str(&format!("* Format: {:?}\n", self.format));
+                if self.names.len() > 1 {
+                    let aliases: Vec<String> = self.names.iter().skip(1)
+                        .map(|name| format.code(name))
+                        .collect();
+                    result.push_str(&format!("* Alternative names: {}\n", aliases.join(", ")));
+                }
+                if let Some(ref deprecation) = self.deprecated {
+                    result.push_str(&format!("* Deprecated: {}\n", deprecation.message.trim()));
+                }
+                let name_of = |id: &str| {
+                    let name = self.attributes.iter()
+                        .find(|attribute| attribute.identifier == id)
+                        .map(|attribute| attribute.default_name())
+                        .unwrap_or(id);
+                    format.code(name)
+                };
+                let mut conflicts: Vec<(&str, &str)> = vec![];
+                for attribute in &self.attributes {
+                    for other in &attribute.requires {
+                        result.push_str(&format!(
+                            "* {} requires {}\n",
+                            name_of(&attribute.identifier),
+                            name_of(other)
+                        ));
+                    }
+                    for other in &attribute.conflicts_with {
+                        if conflicts.contains(&(other.as_str(), attribute.identifier.as_str())) {
+                            continue;
+                        }
+                        conflicts.push((&attribute.identifier, other));
+                        result.push_str(&format!(
+                            "* {} cannot be given together with {}\n",
+                            name_of(&attribute.identifier),
+                            name_of(other)
+                        ));
+                    }
+                }
+                let template_list = |ids: &[String]| {
+                    ids.iter().map(|id| format.code(id)).collect::<Vec<_>>().join(", ")
+                };
+                if let Some(ref allowed) = self.allowed_parents {
+                    result.push_str(&format!("* Can only be used in: {}\n", template_list(allowed)));
+                }
+                if let Some(ref allowed) = self.allowed_children {
+                    result.push_str(&format!("* Can only contain: {}\n", template_list(allowed)));
+                }
+                for group in &self.one_of {
+                    let names: Vec<String> = group.iter().map(|id| name_of(id)).collect();
+                    result.push_str(&format!("* Exactly one of: {}\n", names.join(", ")));
+                }
+                result.push('\n');
+
+                let header = ["Attribute", "Alternative names", "Priority", "Predicate", "Description"];
+                let rows = self.attributes.iter().map(|attribute| {
+                    let mut names: Vec<String> = attribute.names.iter().map(|name| {
+                        if attribute.repeat {
+                            format.code(&format!("{}1", name))
+                        } else {
+                            format.code(name)
+                        }
+                    }).collect();
+                    names.extend(attribute.position.map(|position| format.code(&position.to_string())));
+                    let first = names.remove(0);
+                    let description = match attribute.deprecated {
+                        Some(ref deprecation) => format!(
+                            "Deprecated: {} {}",
+                            deprecation.message.trim(),
+                            attribute.description
+                        ),
+                        None => attribute.description.clone(),
+                    };
+                    vec![
+                        first,
+                        names.join(", "),
+                        format!("{:?}", attribute.priority),
+                        match attribute.context_predicate_name {
+                            Some(ref name) => format!(
+                                "{}, {}",
+                                format.code(&attribute.predicate_name),
+                                format.code(name)
+                            ),
+                            None => format.code(&attribute.predicate_name),
+                        },
+                        format.cell(&description),
+                    ]
+                });
+                match format {
+                    HelpFormat::Wikitext => {
+                        result.push_str("{| class=\"wikitable\"\n");
+                        result.push_str(&format!("! {}\n", header.join(" !! ")));
+                        for row in rows {
+                            result.push_str(&format!("|-\n| {}\n", row.join(" || ")));
+                        }
+                        result.push_str("|}\n");
+                    }
+                    HelpFormat::Markdown => {
+                        result.push_str(&format!("| {} |\n", header.join(" | ")));
+                        result.push_str(&format!("|{}\n", " --- |".repeat(header.len())));
+                        for row in rows {
+                            result.push_str(&format!("| {} |\n", row.join(" | ")));
+                        }
+                    }
+                }
+                result.push('\n');
+                result.push_str(&format.heading(3, "Usage"));
+                result.push('\n');
+                match format {
+                    HelpFormat::Wikitext => {
+                        result.push_str(&format!("<pre>\n{}\n</pre>\n", self.usage()))
+                    }
+                    HelpFormat::Markdown => {
+                        result.push_str(&format!("```\n{}\n```\n", self.usage()))
+                    }
+                }
+                result
+            }
+        }
+
+        /// Render a help page documenting all given templates.
+        pub fn help_page(templates: &[TemplateSpec], format: HelpFormat) -> String {
+            let sections: Vec<String> = templates.iter()
+                .map(|template| template.help_section(format))
+                .collect();
+            sections.join("\n")
+        }
+    }
+}
+
+fn implement_conversions() -> TokenStream {
+    quote! {
+        /// The source span covered by a list of elements.
+        pub fn content_span(content: &[Element]) -> Span {
+            match (content.first(), content.last()) {
+                (Some(first), Some(last)) => Span {
+                    start: first.get_position().start.clone(),
+                    end: last.get_position().end.clone(),
+                },
+                _ => Span::any(),
+            }
+        }
+
+        fn plain_text(attribute: &str, content: &[Element]) -> Result<String, ConversionError> {
+            let mut text = String::new();
+            for element in content {
+                match *element {
+                    Element::Text(ref e) => text.push_str(&e.text),
+                    Element::Paragraph(ref e) => text.push_str(&plain_text(attribute, &e.content)?),
+                    Element::Comment(_) => (),
+                    _ => return Err(ConversionError {
+                        attribute: attribute.into(),
+                        cause: format!("expected plain text, found {}!", element.get_variant_name()),
+                        position: element.get_position().clone(),
+                    }),
+                }
+            }
+            Ok(text)
+        }
+
+        /// Convert attribute content to text without markup.
+        pub fn convert_text(attribute: &str, content: &[Element]) -> Result<String, ConversionError> {
+            Ok(plain_text(attribute, content)?.trim().into())
+        }
+
+        /// Convert attribute content to an integer.
+        pub fn convert_integer(attribute: &str, content: &[Element]) -> Result<i64, ConversionError> {
+            let text = convert_text(attribute, content)?;
+            text.parse().map_err(|_| ConversionError {
+                attribute: attribute.into(),
+                cause: format!("{:?} is not an integer!", text),
+                position: content_span(content),
+            })
+        }
+
+        /// Convert attribute content to a boolean.
+        pub fn convert_boolean(attribute: &str, content: &[Element]) -> Result<bool, ConversionError> {
+            let text = convert_text(attribute, content)?.to_lowercase();
+            match text.as_str() {
+                "true" | "yes" | "ja" | "1" => Ok(true),
+                "false" | "no" | "nein" | "0" => Ok(false),
+                _ => Err(ConversionError {
+                    attribute: attribute.into(),
+                    cause: format!("{:?} is not a boolean value!", text),
+                    position: content_span(content),
+                }),
+            }
+        }
+
+        /// Convert attribute content to one of the allowed (lowercase) values.
+        pub fn convert_enum(
+            attribute: &str,
+            content: &[Element],
+            allowed: &[String],
+        ) -> Result<String, ConversionError> {
+            let text = convert_text(attribute, content)?.to_lowercase();
+            if allowed.contains(&text) {
+                Ok(text)
+            } else {
+                Err(ConversionError {
+                    attribute: attribute.into(),
+                    cause: format!("{:?} is not one of {:?}!", text, allowed),
+                    position: content_span(content),
+                })
+            }
+        }
+
+        /// Convert attribute content to a single math formula.
+        pub fn convert_formula<'e>(
+            attribute: &str,
+            content: &'e [Element],
+        ) -> Result<&'e Formatted, ConversionError> {
+            let mut formulas = vec![];
+            for element in content {
+                match *element {
+                    Element::Formatted(ref e) if e.markup == MarkupType::Math => formulas.push(e),
+                    Element::Paragraph(ref e) => {
+                        formulas.push(convert_formula(attribute, &e.content)?)
+                    }
+                    Element::Text(ref e) if e.text.trim().is_empty() => (),
+                    Element::Comment(_) => (),
+                    _ => return Err(ConversionError {
+                        attribute: attribute.into(),
+                        cause: format!("expected a formula, found {}!", element.get_variant_name()),
+                        position: element.get_position().clone(),
+                    }),
+                }
+            }
+            if formulas.len() == 1 {
+                Ok(formulas.remove(0))
+            } else {
+                Err(ConversionError {
+                    attribute: attribute.into(),
+                    cause: format!("expected exactly one formula, found {}!", formulas.len()),
+                    position: content_span(content),
+                })
+            }
+        }
+
+        /// Namespace prefixes of file names (lowercase).
+        pub const FILE_NAMESPACES: [&str; 4] = ["file", "image", "datei", "bild"];
+
+        /// Convert attribute content to a file name, removing the namespace prefix.
+        pub fn convert_file(attribute: &str, content: &[Element]) -> Result<String, ConversionError> {
+            let text = convert_text(attribute, content)?;
+            let name = match text.find(':') {
+                Some(index) if FILE_NAMESPACES
+                    .contains(&text[..index].trim().to_lowercase().as_str()) =>
+                {
+                    text[index + 1..].trim().to_string()
+                }
+                _ => text,
+            };
+            if name.is_empty() {
+                return Err(ConversionError {
+                    attribute: attribute.into(),
+                    cause: "file name must not be empty!".into(),
+                    position: content_span(content),
+                });
+            }
+            Ok(name)
+        }
+    }
+}
+
+/// A non-empty line of a specification file.
+struct SourceLine<'s> {
+    number: usize,
+    indent: usize,
+    text: &'s str,
+}
+
+impl<'s> SourceLine<'s> {
+    /// Column and text of the mapping key of this line, after a list item dash.
+    fn key(&self) -> (usize, &'s str) {
+        let key = self.text.trim_start_matches('-').trim_start();
+        (self.indent + self.text.len() - key.len(), key)
+    }
+
+    /// The value of this line if its (possibly quoted) key is `key`.
+    fn value_of(&self, key: &str) -> Option<&'s str> {
+        let (_, text) = self.key();
+        let colon = text.find(':')?;
+        if unquote(&text[..colon]) != key {
+            return None;
+        }
+        Some(unquote(&text[colon + 1..]))
+    }
+
+    /// Column of an `id: <id>` entry of a flow mapping in this line, like `{id: Foo, ...}`.
+    fn flow_id(&self, id: &str) -> Option<usize> {
+        let starts = self
+            .text
+            .char_indices()
+            .filter(|&(_, c)| c == '{' || c == ',')
+            .map(|(index, _)| index + 1);
+        for start in starts {
+            let entry = self.text[start..].trim_start();
+            let colon = match entry.find(':') {
+                Some(colon) => colon,
+                None => continue,
+            };
+            let value = entry[colon + 1..].split([',', '}']).next();
+            if unquote(&entry[..colon]) == "id" && value.map(unquote) == Some(id) {
+                return Some(self.indent + self.text.len() - entry.len() + 1);
+            }
+        }
+        None
+    }
+}
+
+fn unquote(text: &str) -> &str {
+    text.trim().trim_matches(|c| c == '"' || c == '\'')
+}
+
+fn source_lines(source: &str) -> Vec<SourceLine<'_>> {
+    source
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let text = line.trim();
+            if text.is_empty() || text.starts_with('#') {
+                return None;
+            }
+            let indent = line.len() - line.trim_start().len();
+            Some(SourceLine {
+                number: index + 1,
+                indent,
+                text,
+            })
+        })
+        .collect()
+}
+
+/// The lines nested in the mapping key at `lines[index]`, including the rest of
+/// the key line itself if it has a flow value.
+fn nested<'l, 's>(lines: &'l [SourceLine<'s>], index: usize) -> &'l [SourceLine<'s>] {
+    let (column, _) = lines[index].key();
+    let rest = &lines[index + 1..];
+    let end = rest
+        .iter()
+        .position(|line| {
+            line.indent < column || (line.indent == column && !line.text.starts_with('-'))
+        })
+        .unwrap_or(rest.len());
+    let block = lines[index].text.ends_with(':');
+    &lines[index + block as usize..index + 1 + end]
+}
+
+/// The position of the identifier `id` in a list of mappings, in block or flow style,
+/// and the lines of its entry.
+fn find_entry<'l, 's>(
+    lines: &'l [SourceLine<'s>],
+    id: &str,
+) -> Option<((usize, usize), &'l [SourceLine<'s>])> {
+    let column = lines
+        .iter()
+        .filter(|line| line.text.starts_with('-'))
+        .map(|line| line.indent)
+        .min();
+    let column = match column {
+        Some(column) => column,
+        // a flow sequence of flow mappings.
+        None => return find_flow_id(lines, id).map(|position| (position, lines)),
+    };
+    let starts: Vec<usize> = (0..lines.len())
+        .filter(|&index| lines[index].indent == column && lines[index].text.starts_with('-'))
+        .collect();
+    for (number, &start) in starts.iter().enumerate() {
+        let end = starts.get(number + 1).cloned().unwrap_or(lines.len());
+        let entry = &lines[start..end];
+        let (key_column, key) = entry[0].key();
+        let found = if key.starts_with('{') {
+            find_flow_id(entry, id)
+        } else {
+            entry
+                .iter()
+                .find(|line| line.key().0 == key_column && line.value_of("id") == Some(id))
+                .map(|line| (line.number, key_column + 1))
+        };
+        if let Some(position) = found {
+            return Some((position, entry));
+        }
+    }
+    None
+}
+
+fn find_flow_id(lines: &[SourceLine], id: &str) -> Option<(usize, usize)> {
+    lines
+        .iter()
+        .find_map(|line| line.flow_id(id).map(|column| (line.number, column)))
+}
+
+/// The lines nested in the top-level key `key`, all lines for the list form.
+fn top_level<'l, 's>(lines: &'l [SourceLine<'s>], key: &str) -> Option<&'l [SourceLine<'s>]> {
+    match lines
+        .iter()
+        .position(|line| line.indent == 0 && line.value_of(key).is_some())
+    {
+        Some(index) => Some(nested(lines, index)),
+        None if key == "templates"
+            && lines
+                .iter()
+                .all(|l| l.indent > 0 || l.text.starts_with('-')) =>
+        {
+            Some(lines)
+        }
+        None => None,
+    }
+}
+
+/// Find line and column of the definition of a template, an attribute group
+/// or an attribute in one of them.
+fn locate(
+    source: &str,
+    origin: &AttributeOrigin,
+    attribute: Option<&str>,
+) -> Option<(usize, usize)> {
+    let lines = source_lines(source);
+    let (found, attributes) = match *origin {
+        AttributeOrigin::Template(ref template) => {
+            let (found, entry) = find_entry(top_level(&lines, "templates")?, template)?;
+            let (column, key) = entry[0].key();
+            let attributes = if key.starts_with('{') {
+                // the rest of the entry, starting at the template identifier.
+                let index = entry.iter().position(|line| line.number == found.0)?;
+                Some(&entry[index..])
+            } else {
+                entry
+                    .iter()
+                    .position(|line| {
+                        line.key().0 == column && line.value_of("attributes").is_some()
+                    })
+                    .map(|index| nested(entry, index))
+            };
+            (found, attributes)
+        }
+        AttributeOrigin::Group(ref group) => {
+            let groups = top_level(&lines, "attribute_groups")?;
+            let column = groups.first()?.indent;
+            let index = groups
+                .iter()
+                .position(|line| line.indent == column && line.value_of(group).is_some())?;
+            let found = (groups[index].number, groups[index].key().0 + 1);
+            (found, Some(nested(groups, index)))
+        }
+    };
+    match attribute {
+        Some(attribute) => find_entry(attributes?, attribute).map(|(position, _)| position),
+        None => Some(found),
+    }
+}
+
+/// Create an error message pointing to the origin of a specification error.
+/// `merged` is the specification of all files, if they could be merged.
+fn describe_error(
+    spec: &str,
+    root: &Path,
+    files: &[LoadedSpec],
+    merged: Option<&SpecFile>,
+    error: &SpecError,
+) -> String {
+    let template = match error.template {
+        Some(ref template) => template,
+        None => return format!("{}: {}", spec, error.message),
+    };
+    let attribute = error.attribute.as_deref();
+    // inherited attributes are reported where they are defined.
+    let origin = attribute
+        .and_then(|attribute| merged?.attribute_origin(template, attribute))
+        .filter(|origin| *origin != AttributeOrigin::Template(template.clone()));
+    let located = origin
+        .clone()
+        .unwrap_or_else(|| AttributeOrigin::Template(template.clone()));
+    // duplicates are reported for the last definition.
+    let loaded = files.iter().rev().find(|loaded| match located {
+        AttributeOrigin::Template(ref template) => loaded
+            .file
+            .templates
+            .iter()
+            .any(|t| t.identifier == *template),
+        AttributeOrigin::Group(ref group) => loaded.file.attribute_groups.contains_key(group),
+    });
+
+    let mut message = match loaded {
+        Some(loaded) => {
+            let path = loaded.path.strip_prefix(root).unwrap_or(&loaded.path);
+            let mut message = path.display().to_string();
+            if let Some((line, column)) = locate(&loaded.source, &located, attribute) {
+                message.push_str(&format!(":{}:{}", line, column));
+            }
+            message
+        }
+        None => spec.to_string(),
+    };
+    message.push_str(&format!(": template {:?}", template));
+    if let Some(ref attribute) = error.attribute {
+        message.push_str(&format!(", attribute {:?}", attribute));
+    }
+    if let Some(ref origin) = origin {
+        message.push_str(&format!(" (from {})", origin));
+    }
+    message.push_str(&format!(": {}", error.message));
+    message
+}
+
+/// Describe a failure to load the specification, with paths relative to `root`.
+fn describe_load_error(root: &Path, error: LoadError) -> String {
+    let relative = |path: PathBuf| {
+        path.strip_prefix(root)
+            .map(Path::to_path_buf)
+            .unwrap_or(path)
+    };
+    match error {
+        LoadError::Io(path, cause) => LoadError::Io(relative(path), cause),
+        LoadError::Yaml(path, cause) => LoadError::Yaml(relative(path), cause),
+    }
+    .to_string()
+}
+
+/// Merge, resolve and check the loaded specification files `spec` consists of.
+fn check_files(
+    spec: &str,
+    root: &Path,
+    files: &[LoadedSpec],
+) -> Result<Vec<SpecTemplate>, Vec<String>> {
+    let describe = |errors: Vec<SpecError>, merged: Option<&SpecFile>| -> Vec<String> {
+        errors
+            .iter()
+            .map(|error| describe_error(spec, root, files, merged, error))
+            .collect()
+    };
+    let merged = match merge_files(files) {
+        Ok(merged) => merged,
+        Err(errors) => return Err(describe(errors, None)),
+    };
+    let templates = match merged.resolve() {
+        Ok(templates) => templates,
+        Err(errors) => return Err(describe(errors, Some(&merged))),
+    };
+
+    let mut errors = check_spec(&templates);
+    for template in &templates {
+        for attribute in &template.attributes {
+            // rust keywords are not caught by `check_spec`.
+            if syn::parse_str::<Ident>(&attribute.identifier).is_err() {
+                errors.push(SpecError {
+                    template: Some(template.identifier.clone()),
+                    attribute: Some(attribute.identifier.clone()),
+                    message: "attribute identifier cannot be used as field name!".into(),
+                });
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(templates)
+    } else {
+        Err(describe(errors, Some(&merged)))
+    }
+}
+
+/// Read and check the specification, returning error messages on failure.
+/// Also returns the paths of all files read.
+fn load_spec(path_lit: &LitStr) -> Result<(Vec<SpecTemplate>, Vec<PathBuf>), Vec<String>> {
+    let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".into()));
+    let files = match load_files(&root.join(path_lit.value())) {
+        Ok(ref files) if files.is_empty() => {
+            return Err(vec![format!(
+                "no specification files found in {:?}!",
+                path_lit.value()
+            )])
+        }
+        Ok(files) => files,
+        Err(error) => return Err(vec![describe_load_error(&root, error)]),
+    };
+    let templates = check_files(&path_lit.value(), &root, &files)?;
+    Ok((
+        templates,
+        files.into_iter().map(|loaded| loaded.path).collect(),
+    ))
+}
+
+#[proc_macro]
+pub fn template_spec(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let path_lit: LitStr = match syn::parse(input) {
+        Ok(path_lit) => path_lit,
+        Err(_) => {
+            return quote! {
+                compile_error!("template_spec! expects a string literal with the spec path!");
             }
+            .into()
         }
+    };
+
+    let (templates, paths) = match load_spec(&path_lit) {
+        Ok(loaded) => loaded,
+        Err(messages) => {
+            return quote! {
+                #( compile_error!(#messages); )*
+            }
+            .into()
+        }
+    };
+
+    let template_id = implement_template_id(&templates);
+    let template_impls = implement_templates(&templates);
+    let spec_func = implement_spec_list(&templates);
+    let template_parsing = implement_template_parsing(&templates);
+    let validation = implement_validation();
+    let argument_check = implement_argument_check();
+    let spec_meta = implement_spec_meta();
+
+    // makes cargo rebuild when one of the files changes.
+    let paths = paths.iter().map(|path| path.display().to_string());
+    let implementation = quote! {
+        #( const _: &str = include_str!(#paths); )*
+
+        use mediawiki_parser::{Element, Span, Template};
+        use serde_derive::{Serialize};
+
+        #spec_meta
 
         use self::spec_meta::*;
 
@@ -485,7 +2976,12 @@ pub fn template_spec(input: proc_macro::TokenStream) -> proc_macro::TokenStream
         #template_id
         #spec_func
         #template_parsing
+        #validation
+        #argument_check
         #( #template_impls )*
     };
     implementation.into()
 }
+
+#[cfg(test)]
+mod test;
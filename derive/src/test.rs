@@ -0,0 +1,198 @@
+use super::*;
+
+const ROOT: &str = "/crate";
+
+fn loaded(path: &str, source: &str) -> LoadedSpec {
+    LoadedSpec {
+        path: Path::new(ROOT).join(path),
+        source: source.to_string(),
+        file: SpecFile::from_yaml(source).expect("test spec must parse"),
+    }
+}
+
+/// A template list entry in block style with the given attribute entries.
+fn template(id: &str, names: &str, attributes: &[String]) -> String {
+    let mut source = format!(
+        "  - id: {}\n    names: {}\n    description: A template.\n    format: inline\n",
+        id, names
+    );
+    if attributes.is_empty() {
+        source.push_str("    attributes: []\n");
+    } else {
+        source.push_str("    attributes:\n");
+        for attribute in attributes {
+            source.push_str(attribute);
+        }
+    }
+    source
+}
+
+/// An attribute list entry in block style.
+fn attribute(id: &str, names: &str) -> String {
+    format!(
+        "      - id: {}\n        names: {}\n        priority: optional\n        \
+         predicate: builtin::non_empty\n        description: An attribute.\n",
+        id, names
+    )
+}
+
+/// Check the given files and return the first error message.
+fn first_error(files: &[LoadedSpec]) -> String {
+    match check_files("spec", Path::new(ROOT), files) {
+        Ok(_) => panic!("expected a specification error"),
+        Err(messages) => messages[0].clone(),
+    }
+}
+
+fn assert_prefix(message: &str, prefix: &str) {
+    assert!(
+        message.starts_with(prefix),
+        "{:?} does not start with {:?}",
+        message,
+        prefix
+    );
+}
+
+#[test]
+fn lowercase_template_id() {
+    let source = format!("templates:\n{}", template("foo", "[foo]", &[]));
+    let message = first_error(&[loaded("a.yml", &source)]);
+    assert_prefix(&message, "a.yml:2:5: template \"foo\": ");
+    assert!(message.contains("uppercase"));
+}
+
+#[test]
+fn empty_names() {
+    let source = format!(
+        "templates:\n{}{}",
+        template("Foo", "[foo]", &[]),
+        template("Bar", "[]", &[])
+    );
+    let message = first_error(&[loaded("a.yml", &source)]);
+    assert_prefix(&message, "a.yml:7:5: template \"Bar\": ");
+    assert!(message.contains("at least one name"));
+}
+
+#[test]
+fn duplicate_template_id() {
+    let first = format!("templates:\n{}", template("Foo", "[foo]", &[]));
+    let second = format!(
+        "templates:\n{}{}",
+        template("Bar", "[bar]", &[]),
+        template("Foo", "[other]", &[])
+    );
+    let message = first_error(&[loaded("a.yml", &first), loaded("b.yml", &second)]);
+    // duplicates are reported for the last definition.
+    assert_prefix(&message, "b.yml:7:5: template \"Foo\": ");
+}
+
+#[test]
+fn duplicate_name_across_templates() {
+    let source = format!(
+        "templates:\n{}{}",
+        template("Foo", "[foo]", &[]),
+        template("Bar", "[bar, \"Foo \"]", &[])
+    );
+    let message = first_error(&[loaded("a.yml", &source)]);
+    assert_prefix(&message, "a.yml:7:5: template \"Bar\": ");
+    assert!(message.contains("\"Foo \""));
+}
+
+#[test]
+fn normalized_duplicate_attribute_names() {
+    let attributes = [attribute("a", "[a]"), attribute("b", "[\" A\"]")];
+    let source = format!("templates:\n{}", template("Foo", "[foo]", &attributes));
+    let message = first_error(&[loaded("a.yml", &source)]);
+    assert_prefix(&message, "a.yml:12:9: template \"Foo\", attribute \"b\": ");
+}
+
+#[test]
+fn keyword_identifier() {
+    let attributes = [attribute("type", "[type]")];
+    let source = format!("templates:\n{}", template("Foo", "[foo]", &attributes));
+    let message = first_error(&[loaded("a.yml", &source)]);
+    assert_prefix(
+        &message,
+        "a.yml:7:9: template \"Foo\", attribute \"type\": ",
+    );
+    assert!(message.contains("field name"));
+}
+
+#[test]
+fn inherited_attribute() {
+    let source = format!(
+        "attribute_groups:\n  common:\n{}\ntemplates:\n{}",
+        attribute("type", "[type]").replace("      ", "    "),
+        template("Foo", "[foo]", &[]).replace(
+            "    attributes: []\n",
+            "    attribute_groups: [common]\n    attributes: []\n"
+        )
+    );
+    let message = first_error(&[loaded("a.yml", &source)]);
+    assert_prefix(
+        &message,
+        "a.yml:3:7: template \"Foo\", attribute \"type\" (from attribute group \"common\"): ",
+    );
+}
+
+#[test]
+fn yaml_syntax_error() {
+    let source = "templates:\n  - id: Foo\n    names: [foo\n";
+    let error = match SpecFile::from_yaml(source) {
+        Ok(_) => panic!("expected a syntax error"),
+        Err(error) => error,
+    };
+    let message = describe_load_error(
+        Path::new(ROOT),
+        LoadError::Yaml(Path::new(ROOT).join("a.yml"), error),
+    );
+    assert_prefix(&message, "a.yml:4:1: cannot parse spec: ");
+}
+
+#[test]
+fn flow_style_entries() {
+    let source = "templates:
+  - {id: Foo, names: [foo], description: A template., format: inline, attributes: []}
+  - id: Bar
+    names: [bar]
+    description: A template.
+    format: inline
+    attributes: [{id: a, names: [a], priority: optional, predicate: 'builtin::non_empty',
+                  description: An attribute.},
+                 {id: type, names: [type], priority: optional, predicate: 'builtin::non_empty',
+                  description: An attribute.}]
+";
+    let message = first_error(&[loaded("a.yml", source)]);
+    assert_prefix(
+        &message,
+        "a.yml:9:19: template \"Bar\", attribute \"type\": ",
+    );
+
+    let source = "templates:
+  - {id: Foo, names: [foo], description: A template., format: inline, attributes: []}
+  - {id: bar, names: [bar], description: A template., format: inline, attributes: []}
+";
+    let message = first_error(&[loaded("a.yml", source)]);
+    assert_prefix(&message, "a.yml:3:6: template \"bar\": ");
+}
+
+#[test]
+fn quoted_keys() {
+    let source = "\"templates\":
+  - \"id\": Foo
+    'names': [foo]
+    description: A template.
+    format: inline
+    \"attributes\":
+      - 'id': \"type\"
+        names: [type]
+        priority: optional
+        predicate: builtin::non_empty
+        description: An attribute.
+";
+    let message = first_error(&[loaded("a.yml", source)]);
+    assert_prefix(
+        &message,
+        "a.yml:7:9: template \"Foo\", attribute \"type\": ",
+    );
+}
@@ -0,0 +1,966 @@
+//! The template specification format, shared by the `template_spec!` macro
+//! and the runtime `SpecRegistry` of `mwparser_utils`.
+
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecFormat {
+    Block,
+    Box,
+    Inline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecPriority {
+    Required,
+    /// Optional, but a warning is reported if missing.
+    Recommended,
+    Optional,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecSeverity {
+    Error,
+    Warning,
+    Info,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecType {
+    Text,
+    Integer,
+    Boolean,
+    Enum(Vec<String>),
+    Wikitext,
+    Formula,
+    File,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpecDefault {
+    Text(String),
+    Wikitext(String),
+}
+
+/// Marks a template, attribute or name as deprecated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecDeprecation {
+    pub message: String,
+    /// Identifier of the template or attribute to use instead.
+    /// For names, the name to use instead of the default name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecTemplate {
+    #[serde(rename = "id")]
+    pub identifier: String,
+    pub description: String,
+    pub names: Vec<String>,
+    pub attributes: Vec<SpecAttribute>,
+    pub format: SpecFormat,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<SpecDeprecation>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub deprecated_names: BTreeMap<String, SpecDeprecation>,
+    /// Groups of attribute identifiers of which exactly one must be given.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub one_of: Vec<Vec<String>>,
+    /// Identifiers of the templates this template may only be used in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_parents: Option<Vec<String>>,
+    /// Identifiers of the only templates which may be used in this template.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_children: Option<Vec<String>>,
+    /// Identifier of a template whose attributes are inherited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// Names of attribute groups of the specification file whose attributes are included.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attribute_groups: Vec<String>,
+}
+
+/// A specification file, either a list of templates or a mapping
+/// with `templates`, named `attribute_groups` and `include`d files.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct SpecFile {
+    /// Paths of further specification files or directories,
+    /// relative to the directory of this file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attribute_groups: BTreeMap<String, Vec<SpecAttribute>>,
+    #[serde(default)]
+    pub templates: Vec<SpecTemplate>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecAttribute {
+    #[serde(rename = "id")]
+    pub identifier: String,
+    pub description: String,
+    pub names: Vec<String>,
+    pub priority: SpecPriority,
+    pub predicate: String,
+    /// Name of a predicate which also receives the context of the attribute.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_predicate: Option<String>,
+    /// Severity of predicate failures, `error` if not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<SpecSeverity>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<SpecType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<SpecDefault>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<usize>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub repeat: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<SpecDeprecation>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub deprecated_names: BTreeMap<String, SpecDeprecation>,
+    /// Identifiers of attributes which must be given if this attribute is.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<String>,
+    /// Identifiers of attributes which must not be given together with this attribute.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts_with: Vec<String>,
+}
+
+impl SpecFile {
+    /// Parse a specification file in either form.
+    pub fn from_yaml(source: &str) -> Result<SpecFile, serde_yaml::Error> {
+        match serde_yaml::from_str(source)? {
+            serde_yaml::Value::Mapping(_) => serde_yaml::from_str(source),
+            _ => Ok(SpecFile {
+                templates: serde_yaml::from_str(source)?,
+                ..SpecFile::default()
+            }),
+        }
+    }
+
+    /// The templates of this file with `extends` and `attribute_groups` resolved.
+    ///
+    /// Inherited attributes come first, followed by those of the attribute groups
+    /// and the attributes of the template itself. An attribute of the template
+    /// overrides an inherited attribute with the same identifier. An attribute
+    /// inherited from different sources must have the same definition everywhere.
+    pub fn resolve(&self) -> Result<Vec<SpecTemplate>, Vec<SpecError>> {
+        let mut errors = vec![];
+        let mut templates = vec![];
+        for template in &self.templates {
+            match self.resolve_attributes(template, &mut vec![]) {
+                Ok(attributes) => templates.push(SpecTemplate {
+                    attributes: attributes.into_iter().map(|(a, _)| a).collect(),
+                    extends: None,
+                    attribute_groups: vec![],
+                    ..template.clone()
+                }),
+                // errors of extended templates are found again for each extending template.
+                Err(error) => {
+                    if !errors.contains(&error) {
+                        errors.push(error)
+                    }
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(templates)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Where the resolved attribute of a template is defined,
+    /// `None` if the template or attribute does not exist or cannot be resolved.
+    pub fn attribute_origin(&self, template: &str, attribute: &str) -> Option<AttributeOrigin> {
+        let template = self.templates.iter().find(|t| t.identifier == template)?;
+        let attributes = self.resolve_attributes(template, &mut vec![]).ok()?;
+        attributes
+            .into_iter()
+            .find(|(a, _)| a.identifier == attribute)
+            .map(|(_, origin)| origin)
+    }
+
+    /// Attributes of a template with their origin,
+    /// `chain` are the templates extended by it so far.
+    fn resolve_attributes(
+        &self,
+        template: &SpecTemplate,
+        chain: &mut Vec<String>,
+    ) -> Result<Vec<(SpecAttribute, AttributeOrigin)>, SpecError> {
+        chain.push(template.identifier.clone());
+        let mut sources = vec![];
+        if let Some(ref parent_id) = template.extends {
+            if chain.contains(parent_id) {
+                return Err(SpecError::template(
+                    template,
+                    format!(
+                        "cyclic inheritance: {} -> {}!",
+                        chain.join(" -> "),
+                        parent_id
+                    ),
+                ));
+            }
+            let parent = self
+                .templates
+                .iter()
+                .find(|t| t.identifier == *parent_id)
+                .ok_or_else(|| {
+                    SpecError::template(
+                        template,
+                        format!("extended template {:?} does not exist!", parent_id),
+                    )
+                })?;
+            sources.push(self.resolve_attributes(parent, chain)?);
+        }
+        for group in &template.attribute_groups {
+            let attributes = self.attribute_groups.get(group).ok_or_else(|| {
+                SpecError::template(
+                    template,
+                    format!("attribute group {:?} does not exist!", group),
+                )
+            })?;
+            let origin = AttributeOrigin::Group(group.clone());
+            sources.push(
+                attributes
+                    .iter()
+                    .map(|a| (a.clone(), origin.clone()))
+                    .collect(),
+            );
+        }
+
+        let overrides = |attribute: &SpecAttribute| {
+            template
+                .attributes
+                .iter()
+                .any(|a| a.identifier == attribute.identifier)
+        };
+        let mut attributes: Vec<(SpecAttribute, AttributeOrigin)> = vec![];
+        for (attribute, origin) in sources.into_iter().flatten() {
+            match attributes
+                .iter()
+                .position(|(a, _)| a.identifier == attribute.identifier)
+            {
+                Some(index) if attributes[index].0 != attribute && !overrides(&attribute) => {
+                    return Err(SpecError::attribute(
+                        template,
+                        &attribute,
+                        format!(
+                            "conflicting definitions in {} and {}, \
+                             override the attribute to resolve this!",
+                            attributes[index].1, origin
+                        ),
+                    ));
+                }
+                Some(_) => (),
+                None => attributes.push((attribute, origin)),
+            }
+        }
+        let mut overridden = vec![false; attributes.len()];
+        for attribute in &template.attributes {
+            // duplicates within the template itself are left to `check_spec`.
+            let origin = AttributeOrigin::Template(template.identifier.clone());
+            match attributes[..overridden.len()]
+                .iter()
+                .position(|(a, _)| a.identifier == attribute.identifier)
+            {
+                Some(index) if !overridden[index] => {
+                    attributes[index] = (attribute.clone(), origin);
+                    overridden[index] = true;
+                }
+                _ => attributes.push((attribute.clone(), origin)),
+            }
+        }
+        chain.pop();
+        Ok(attributes)
+    }
+}
+
+/// A specification file read from disk.
+#[derive(Debug, Clone)]
+pub struct LoadedSpec {
+    pub path: PathBuf,
+    pub source: String,
+    pub file: SpecFile,
+}
+
+/// Failure to read or parse a specification file.
+#[derive(Debug)]
+pub enum LoadError {
+    Io(PathBuf, io::Error),
+    Yaml(PathBuf, serde_yaml::Error),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Io(ref path, ref error) => {
+                write!(f, "error opening {:?}: {}", path, error)
+            }
+            LoadError::Yaml(ref path, ref error) => match error.location() {
+                Some(location) => write!(
+                    f,
+                    "{}:{}:{}: cannot parse spec: {}",
+                    path.display(),
+                    location.line(),
+                    location.column(),
+                    error
+                ),
+                None => write!(f, "{}: cannot parse spec: {}", path.display(), error),
+            },
+        }
+    }
+}
+
+/// Read a specification file with all files it includes, or all `.yml`
+/// and `.yaml` files of a directory (ordered by name).
+/// Files included more than once are only read the first time.
+pub fn load_files(path: &Path) -> Result<Vec<LoadedSpec>, LoadError> {
+    let mut files = vec![];
+    load_into(path, &mut files)?;
+    Ok(files)
+}
+
+fn load_into(path: &Path, files: &mut Vec<LoadedSpec>) -> Result<(), LoadError> {
+    let io_error = |error| LoadError::Io(path.to_path_buf(), error);
+    if path.is_dir() {
+        let mut entries = vec![];
+        for entry in fs::read_dir(path).map_err(io_error)? {
+            let entry = entry.map_err(io_error)?.path();
+            let extension = entry.extension().and_then(|e| e.to_str());
+            if entry.is_file() && (extension == Some("yml") || extension == Some("yaml")) {
+                entries.push(entry);
+            }
+        }
+        entries.sort();
+        for entry in entries {
+            load_into(&entry, files)?;
+        }
+        return Ok(());
+    }
+    if files.iter().any(|loaded| same_file(&loaded.path, path)) {
+        return Ok(());
+    }
+    let source = fs::read_to_string(path).map_err(io_error)?;
+    let file =
+        SpecFile::from_yaml(&source).map_err(|error| LoadError::Yaml(path.to_path_buf(), error))?;
+    let base = path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+    let include = file.include.clone();
+    files.push(LoadedSpec {
+        path: path.to_path_buf(),
+        source,
+        file,
+    });
+    for included in include {
+        load_into(&base.join(included), files)?;
+    }
+    Ok(())
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Merge loaded files into a single specification.
+/// Templates and attribute groups defined in more than one file are reported.
+pub fn merge_files(files: &[LoadedSpec]) -> Result<SpecFile, Vec<SpecError>> {
+    let mut merged = SpecFile::default();
+    let mut template_files: Vec<(&str, &Path)> = vec![];
+    let mut group_files: Vec<(&str, &Path)> = vec![];
+    let mut errors = vec![];
+    for loaded in files {
+        for template in &loaded.file.templates {
+            let other = template_files
+                .iter()
+                .find(|(id, path)| *id == template.identifier && *path != loaded.path);
+            if let Some((_, other)) = other {
+                errors.push(SpecError::template(
+                    template,
+                    format!(
+                        "template identifier is also defined in {:?}!",
+                        other.display().to_string()
+                    ),
+                ));
+            }
+            template_files.push((&template.identifier, &loaded.path));
+            merged.templates.push(template.clone());
+        }
+        for (name, attributes) in &loaded.file.attribute_groups {
+            if let Some((_, other)) = group_files.iter().find(|(n, _)| n == name) {
+                errors.push(SpecError {
+                    template: None,
+                    attribute: None,
+                    message: format!(
+                        "attribute group {:?} is defined in {:?} and {:?}!",
+                        name,
+                        other.display().to_string(),
+                        loaded.path.display().to_string()
+                    ),
+                });
+            }
+            group_files.push((name, &loaded.path));
+            merged
+                .attribute_groups
+                .insert(name.clone(), attributes.clone());
+        }
+    }
+    if errors.is_empty() {
+        Ok(merged)
+    } else {
+        Err(errors)
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Where the definition of a resolved attribute is found.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeOrigin {
+    /// The attributes of a template, itself or one extended by another template.
+    Template(String),
+    /// An attribute group.
+    Group(String),
+}
+
+impl fmt::Display for AttributeOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AttributeOrigin::Template(ref template) => write!(f, "template {:?}", template),
+            AttributeOrigin::Group(ref group) => write!(f, "attribute group {:?}", group),
+        }
+    }
+}
+
+/// A semantic error in a template specification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecError {
+    pub template: Option<String>,
+    pub attribute: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref template) = self.template {
+            write!(f, "template {:?}", template)?;
+            if let Some(ref attribute) = self.attribute {
+                write!(f, ", attribute {:?}", attribute)?;
+            }
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl SpecError {
+    fn template(template: &SpecTemplate, message: String) -> Self {
+        SpecError {
+            template: Some(template.identifier.clone()),
+            attribute: None,
+            message,
+        }
+    }
+
+    fn attribute(template: &SpecTemplate, attribute: &SpecAttribute, message: String) -> Self {
+        SpecError {
+            template: Some(template.identifier.clone()),
+            attribute: Some(attribute.identifier.clone()),
+            message,
+        }
+    }
+}
+
+fn is_identifier(input: &str) -> bool {
+    let mut chars = input.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => (),
+        _ => return false,
+    }
+    chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Prefix of the built-in predicates in predicate expressions, e.g. `builtin::non_empty`.
+pub const BUILTIN_PREFIX: &str = "builtin::";
+
+/// Names of the built-in predicates, without `BUILTIN_PREFIX`.
+const BUILTIN_PREDICATES: [&str; 8] = [
+    "plain_text_only",
+    "no_templates",
+    "no_block_elements",
+    "only_formulas",
+    "single_paragraph",
+    "non_empty",
+    "no_headings",
+    "only_file_links",
+];
+
+/// A predicate expression, e.g. `all(builtin::no_templates, max_length(80))`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PredicateExpr {
+    /// A predicate function.
+    Name(String),
+    /// A number argument.
+    Integer(u64),
+    /// A predicate with arguments.
+    Call(String, Vec<PredicateExpr>),
+}
+
+impl fmt::Display for PredicateExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PredicateExpr::Name(ref name) => write!(f, "{}", name),
+            PredicateExpr::Integer(value) => write!(f, "{}", value),
+            PredicateExpr::Call(ref name, ref args) => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            }
+        }
+    }
+}
+
+/// Parses a predicate expression. Returns an error message for invalid syntax.
+pub fn parse_predicate(source: &str) -> Result<PredicateExpr, String> {
+    let mut tokens = vec![];
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' || c == ')' || c == ',' {
+            tokens.push(&source[start..=start]);
+            chars.next();
+        } else if c.is_alphanumeric() || c == '_' {
+            let mut end = start;
+            while let Some(&(index, c)) = chars.peek() {
+                if !c.is_alphanumeric() && c != '_' && c != ':' {
+                    break;
+                }
+                end = index + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(&source[start..end]);
+        } else {
+            return Err(format!("unexpected character {:?}!", c));
+        }
+    }
+
+    let mut position = 0;
+    let expr = parse_expression(&tokens, &mut position)?;
+    if let PredicateExpr::Integer(_) = expr {
+        return Err("a number is not a predicate!".into());
+    }
+    match tokens.get(position) {
+        Some(token) => Err(format!("unexpected {:?} after the expression!", token)),
+        None => Ok(expr),
+    }
+}
+
+/// Check the combinators and arguments of a predicate expression.
+fn check_predicate(expr: &PredicateExpr) -> Result<(), String> {
+    let (name, args) = match *expr {
+        PredicateExpr::Name(ref name) if name == "max_length" => {
+            return Err("max_length expects a maximum number of characters!".into())
+        }
+        PredicateExpr::Name(ref name) if ["all", "any", "not"].contains(&name.as_str()) => {
+            return Err(format!("{} expects predicates as arguments!", name))
+        }
+        PredicateExpr::Name(ref name) => match name.strip_prefix(BUILTIN_PREFIX) {
+            Some(builtin) if !BUILTIN_PREDICATES.contains(&builtin) => {
+                return Err(format!("unknown built-in predicate {}!", name))
+            }
+            _ => return Ok(()),
+        },
+        PredicateExpr::Integer(value) => {
+            return Err(format!(
+                "number {} is only allowed as argument of max_length!",
+                value
+            ))
+        }
+        PredicateExpr::Call(ref name, ref args) => (name.as_str(), args),
+    };
+    match (name, args.as_slice()) {
+        ("all", _) | ("any", _) => (),
+        ("not", [_]) => (),
+        ("not", _) => return Err("not expects exactly one predicate!".into()),
+        ("max_length", [PredicateExpr::Integer(_)]) => return Ok(()),
+        ("max_length", _) => return Err("max_length expects exactly one number!".into()),
+        _ => {
+            return Err(format!(
+                "unknown predicate {}(...), only all, any, not and max_length take arguments!",
+                name
+            ))
+        }
+    }
+    args.iter().try_for_each(check_predicate)
+}
+
+fn parse_expression(tokens: &[&str], position: &mut usize) -> Result<PredicateExpr, String> {
+    let token = match tokens.get(*position) {
+        Some(token) => *token,
+        None => return Err("expected a predicate, found the end of input!".into()),
+    };
+    *position += 1;
+    if token.chars().all(|c| c.is_ascii_digit()) {
+        return token
+            .parse()
+            .map(PredicateExpr::Integer)
+            .map_err(|_| format!("number {} is too large!", token));
+    }
+    let name = token.strip_prefix(BUILTIN_PREFIX).unwrap_or(token);
+    if !is_identifier(name) {
+        return Err(format!("expected a predicate, found {:?}!", token));
+    }
+    if tokens.get(*position) != Some(&"(") {
+        return Ok(PredicateExpr::Name(token.into()));
+    }
+    *position += 1;
+    let mut args = vec![];
+    loop {
+        args.push(parse_expression(tokens, position)?);
+        *position += 1;
+        match tokens.get(*position - 1) {
+            Some(&",") => (),
+            Some(&")") => break,
+            Some(other) => return Err(format!("expected \",\" or \")\", found {:?}!", other)),
+            None => return Err(format!("missing \")\" after the arguments of {}!", token)),
+        }
+    }
+    Ok(PredicateExpr::Call(token.into(), args))
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Checks that deprecated names are alternative names and their replacements exist.
+fn check_deprecated_names(
+    names: &[String],
+    deprecated_names: &BTreeMap<String, SpecDeprecation>,
+) -> Vec<String> {
+    let normalized: Vec<String> = names.iter().map(|n| normalize_name(n)).collect();
+    let mut messages = vec![];
+    for (name, deprecation) in deprecated_names {
+        match normalized.iter().position(|n| *n == normalize_name(name)) {
+            None => messages.push(format!("deprecated name {:?} is not a name!", name)),
+            Some(0) => messages.push(format!("the default name {:?} cannot be deprecated!", name)),
+            Some(_) => (),
+        }
+        if let Some(ref replacement) = deprecation.replacement {
+            let normalized_replacement = normalize_name(replacement);
+            let current = normalized.contains(&normalized_replacement)
+                && !deprecated_names
+                    .keys()
+                    .any(|n| normalize_name(n) == normalized_replacement);
+            if !current {
+                messages.push(format!(
+                    "replacement {:?} of deprecated name {:?} is not a current name!",
+                    replacement, name
+                ));
+            }
+        }
+    }
+    messages
+}
+
+/// Checks if a plain text default value is valid for the attribute type.
+fn check_default(kind: Option<&SpecType>, default: &SpecDefault) -> Option<String> {
+    let text = match *default {
+        SpecDefault::Text(ref text) => normalize_name(text),
+        SpecDefault::Wikitext(_) => return None,
+    };
+    let valid = match kind {
+        Some(SpecType::Integer) => text.parse::<i64>().is_ok(),
+        Some(SpecType::Boolean) => {
+            ["true", "yes", "ja", "1", "false", "no", "nein", "0"].contains(&text.as_str())
+        }
+        Some(SpecType::Enum(values)) => values.iter().any(|v| normalize_name(v) == text),
+        _ => true,
+    };
+    if valid {
+        None
+    } else {
+        Some(format!(
+            "default value {:?} does not match the attribute type!",
+            text
+        ))
+    }
+}
+
+/// Checks a list of templates for naming errors and ambiguities.
+pub fn check_spec(templates: &[SpecTemplate]) -> Vec<SpecError> {
+    let mut errors = vec![];
+    let mut template_ids: Vec<&str> = vec![];
+    let mut template_names: Vec<(String, &str)> = vec![];
+
+    for template in templates {
+        let first_uppercase = template
+            .identifier
+            .chars()
+            .next()
+            .map(|c| c.is_uppercase())
+            .unwrap_or(false);
+
+        if !first_uppercase || !is_identifier(&template.identifier) {
+            errors.push(SpecError::template(
+                template,
+                "template identifiers must be valid identifiers \
+                 starting with an uppercase character!"
+                    .into(),
+            ));
+        }
+
+        if template_ids.contains(&template.identifier.as_str()) {
+            errors.push(SpecError::template(
+                template,
+                "template identifier is defined more than once!".into(),
+            ));
+        }
+        template_ids.push(&template.identifier);
+
+        if template.names.is_empty() {
+            errors.push(SpecError::template(
+                template,
+                "templates must have at least one name!".into(),
+            ));
+        }
+
+        for name in &template.names {
+            let normalized = normalize_name(name);
+            if let Some(&(_, other)) = template_names.iter().find(|(n, _)| *n == normalized) {
+                errors.push(SpecError::template(
+                    template,
+                    format!("template name {:?} is already used by {:?}!", name, other),
+                ));
+            }
+            template_names.push((normalized, &template.identifier));
+        }
+
+        for message in check_deprecated_names(&template.names, &template.deprecated_names) {
+            errors.push(SpecError::template(template, message));
+        }
+
+        let mut attribute_ids: Vec<&str> = vec![];
+        let mut attribute_names: Vec<(String, &str)> = vec![];
+
+        for attribute in &template.attributes {
+            if attribute.identifier.chars().any(|c| c.is_uppercase())
+                || !is_identifier(&attribute.identifier)
+            {
+                errors.push(SpecError::attribute(
+                    template,
+                    attribute,
+                    "attribute identifiers must be valid lowercase identifiers!".into(),
+                ));
+            }
+
+            if attribute_ids.contains(&attribute.identifier.as_str()) {
+                errors.push(SpecError::attribute(
+                    template,
+                    attribute,
+                    "attribute identifier is defined more than once!".into(),
+                ));
+            }
+            attribute_ids.push(&attribute.identifier);
+
+            if attribute.names.is_empty() {
+                errors.push(SpecError::attribute(
+                    template,
+                    attribute,
+                    "attributes must have at least one name!".into(),
+                ));
+            }
+
+            if attribute.repeat && (attribute.position.is_some() || attribute.default.is_some()) {
+                errors.push(SpecError::attribute(
+                    template,
+                    attribute,
+                    "repeated attributes cannot have a position or default value!".into(),
+                ));
+            }
+
+            let positional = attribute.position.map(|p| p.to_string());
+            if attribute.position == Some(0) {
+                errors.push(SpecError::attribute(
+                    template,
+                    attribute,
+                    "argument positions start at 1!".into(),
+                ));
+            }
+
+            for name in attribute.names.iter().chain(positional.iter()) {
+                let normalized = normalize_name(name);
+                if let Some(&(_, other)) = attribute_names.iter().find(|(n, _)| *n == normalized) {
+                    errors.push(SpecError::attribute(
+                        template,
+                        attribute,
+                        format!("attribute name {:?} is already used by {:?}!", name, other),
+                    ));
+                }
+                attribute_names.push((normalized, &attribute.identifier));
+            }
+
+            if attribute.kind == Some(SpecType::Enum(vec![])) {
+                errors.push(SpecError::attribute(
+                    template,
+                    attribute,
+                    "enumerations must have at least one allowed value!".into(),
+                ));
+            }
+
+            if let Some(ref default) = attribute.default {
+                if attribute.priority == SpecPriority::Required {
+                    errors.push(SpecError::attribute(
+                        template,
+                        attribute,
+                        "required attributes cannot have a default value!".into(),
+                    ));
+                }
+                if let Some(message) = check_default(attribute.kind.as_ref(), default) {
+                    errors.push(SpecError::attribute(template, attribute, message));
+                }
+            }
+
+            let expr = parse_predicate(&attribute.predicate);
+            if let Err(message) = expr.and_then(|expr| check_predicate(&expr)) {
+                errors.push(SpecError::attribute(
+                    template,
+                    attribute,
+                    format!("invalid predicate {:?}: {}", attribute.predicate, message),
+                ));
+            }
+            if let Some(ref name) = attribute.context_predicate {
+                if !is_identifier(name) {
+                    errors.push(SpecError::attribute(
+                        template,
+                        attribute,
+                        format!("context predicate {:?} is not a valid identifier!", name),
+                    ));
+                }
+            }
+
+            for message in check_deprecated_names(&attribute.names, &attribute.deprecated_names) {
+                errors.push(SpecError::attribute(template, attribute, message));
+            }
+
+            let replacement = attribute
+                .deprecated
+                .as_ref()
+                .and_then(|d| d.replacement.as_ref());
+            if let Some(replacement) = replacement {
+                let valid = template
+                    .attributes
+                    .iter()
+                    .any(|a| a.identifier == *replacement && a.identifier != attribute.identifier);
+                if !valid {
+                    errors.push(SpecError::attribute(
+                        template,
+                        attribute,
+                        format!("replacement {:?} is not another attribute!", replacement),
+                    ));
+                }
+            }
+
+            for other in attribute.requires.iter().chain(&attribute.conflicts_with) {
+                let valid = template
+                    .attributes
+                    .iter()
+                    .any(|a| a.identifier == *other && a.identifier != attribute.identifier);
+                if !valid {
+                    errors.push(SpecError::attribute(
+                        template,
+                        attribute,
+                        format!(
+                            "constraint refers to {:?}, which is not another attribute!",
+                            other
+                        ),
+                    ));
+                }
+            }
+            if let Some(other) = attribute
+                .requires
+                .iter()
+                .find(|id| attribute.conflicts_with.contains(id))
+            {
+                errors.push(SpecError::attribute(
+                    template,
+                    attribute,
+                    format!("attribute {:?} cannot be required and conflicting!", other),
+                ));
+            }
+        }
+
+        for group in &template.one_of {
+            if group.len() < 2 {
+                errors.push(SpecError::template(
+                    template,
+                    "one_of groups must have at least two attributes!".into(),
+                ));
+            }
+            for id in group {
+                match template.attributes.iter().find(|a| a.identifier == *id) {
+                    None => errors.push(SpecError::template(
+                        template,
+                        format!("one_of group refers to unknown attribute {:?}!", id),
+                    )),
+                    Some(attribute) if attribute.priority == SpecPriority::Required => {
+                        errors.push(SpecError::attribute(
+                            template,
+                            attribute,
+                            "required attributes cannot be part of a one_of group!".into(),
+                        ))
+                    }
+                    Some(_) => (),
+                }
+            }
+        }
+    }
+
+    for template in templates {
+        let allowed = template
+            .allowed_parents
+            .iter()
+            .chain(template.allowed_children.iter())
+            .flatten();
+        for id in allowed {
+            if !templates.iter().any(|t| t.identifier == *id) {
+                errors.push(SpecError::template(
+                    template,
+                    format!("allowed template {:?} does not exist!", id),
+                ));
+            }
+        }
+
+        let replacement = template
+            .deprecated
+            .as_ref()
+            .and_then(|d| d.replacement.as_ref());
+        if let Some(replacement) = replacement {
+            if *replacement == template.identifier
+                || !templates.iter().any(|t| t.identifier == *replacement)
+            {
+                errors.push(SpecError::template(
+                    template,
+                    format!("replacement {:?} is not another template!", replacement),
+                ));
+            }
+        }
+    }
+    errors
+}
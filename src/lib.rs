@@ -1,5 +1,8 @@
 //! This library provides common, Mathe-für-Nicht-Freaks specific code.
 
+pub mod registry;
+pub mod spec;
+pub mod templatedata;
 pub mod transformations;
 mod util;
 
@@ -0,0 +1,357 @@
+//! Template specifications loaded at runtime.
+
+use crate::util::{extract_plain_text, find_arg};
+use mediawiki_parser::*;
+use mwparser_utils_spec::{
+    check_spec, load_files, merge_files, parse_predicate, LoadError, PredicateExpr, SpecError,
+    SpecFile, SpecFormat, SpecPriority, SpecTemplate,
+};
+use serde_derive::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Failure to load a specification into a `SpecRegistry`.
+#[derive(Debug)]
+pub enum RegistryError {
+    Io(io::Error),
+    Yaml(serde_yaml::Error),
+    Spec(Vec<SpecError>),
+    UnknownPredicate {
+        template: String,
+        attribute: String,
+        predicate: String,
+    },
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RegistryError::Io(ref error) => write!(f, "cannot read spec: {}", error),
+            RegistryError::Yaml(ref error) => write!(f, "cannot parse spec: {}", error),
+            RegistryError::Spec(ref errors) => {
+                for error in errors {
+                    writeln!(f, "{}", error)?;
+                }
+                Ok(())
+            }
+            RegistryError::UnknownPredicate {
+                ref template,
+                ref attribute,
+                ref predicate,
+            } => write!(
+                f,
+                "template {:?}, attribute {:?}: unknown predicate {:?}!",
+                template, attribute, predicate
+            ),
+        }
+    }
+}
+
+/// A template specification loaded at runtime, in the same format as
+/// used by the `template_spec!` macro.
+///
+/// Predicates are looked up by name in a table of registered predicates,
+/// expressions like `all(builtin::no_templates, max_length(80))` are built from them when loading.
+/// `P` usually is the `Predicate` type of the generated `spec_meta` module.
+pub struct SpecRegistry<'p, P: ?Sized> {
+    templates: Vec<SpecTemplate>,
+    predicates: HashMap<String, &'p P>,
+    /// Predicates of the attributes, by template and attribute identifier.
+    attribute_predicates: HashMap<(String, String), DynamicPredicate<'p, P>>,
+}
+
+/// The predicate expression of an attribute, built from registered predicates.
+pub enum DynamicPredicate<'p, P: ?Sized> {
+    Registered(&'p P),
+    All(Vec<DynamicPredicate<'p, P>>),
+    Any(Vec<DynamicPredicate<'p, P>>),
+    /// The negated predicate and its expression, for the error message.
+    Not(Box<DynamicPredicate<'p, P>>, String),
+    MaxLength(usize),
+}
+
+/// A predicate failure, like the `PredError` of `spec_meta`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DynamicPredError<'e> {
+    pub tree: Option<&'e Element>,
+    pub cause: String,
+}
+
+/// A template recognized by a `SpecRegistry`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DynamicTemplate<'e, 's> {
+    pub spec: &'s SpecTemplate,
+    pub present: Vec<DynamicAttribute<'e>>,
+}
+
+/// The value of a template attribute recognized by a `SpecRegistry`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DynamicAttribute<'e> {
+    pub name: String,
+    pub priority: SpecPriority,
+    pub value: &'e [Element],
+    pub position: Span,
+}
+
+impl<'p, P: ?Sized> Default for SpecRegistry<'p, P> {
+    fn default() -> Self {
+        SpecRegistry {
+            templates: vec![],
+            predicates: HashMap::new(),
+            attribute_predicates: HashMap::new(),
+        }
+    }
+}
+
+impl<'p, P: ?Sized> SpecRegistry<'p, P> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make a predicate available to specifications loaded afterwards.
+    pub fn register_predicate(&mut self, name: &str, predicate: &'p P) {
+        self.predicates.insert(name.into(), predicate);
+    }
+
+    /// Load templates from a specification file with the files it includes,
+    /// or from all specification files of a directory.
+    pub fn load_file<F: AsRef<Path>>(&mut self, path: F) -> Result<(), RegistryError> {
+        let files = load_files(path.as_ref()).map_err(|error| match error {
+            LoadError::Io(_, error) => RegistryError::Io(error),
+            LoadError::Yaml(_, error) => RegistryError::Yaml(error),
+        })?;
+        self.load(merge_files(&files).map_err(RegistryError::Spec)?)
+    }
+
+    /// Load templates from a specification string. Includes are ignored.
+    pub fn load_str(&mut self, source: &str) -> Result<(), RegistryError> {
+        self.load(SpecFile::from_yaml(source).map_err(RegistryError::Yaml)?)
+    }
+
+    /// Add the templates of a specification, `extends` can only refer to templates
+    /// of the same specification. The registry is left unchanged if it is invalid.
+    fn load(&mut self, file: SpecFile) -> Result<(), RegistryError> {
+        let templates = file.resolve().map_err(RegistryError::Spec)?;
+
+        let mut combined = self.templates.clone();
+        combined.extend(templates.iter().cloned());
+        let errors = check_spec(&combined);
+        if !errors.is_empty() {
+            return Err(RegistryError::Spec(errors));
+        }
+
+        let mut attribute_predicates = vec![];
+        for template in &templates {
+            for attribute in &template.attributes {
+                let expr = parse_predicate(&attribute.predicate)
+                    .expect("predicate expressions are checked by check_spec");
+                let predicate = self.build_predicate(&expr).map_err(|predicate| {
+                    RegistryError::UnknownPredicate {
+                        template: template.identifier.clone(),
+                        attribute: attribute.identifier.clone(),
+                        predicate,
+                    }
+                })?;
+                let key = (template.identifier.clone(), attribute.identifier.clone());
+                attribute_predicates.push((key, predicate));
+            }
+        }
+
+        self.templates = combined;
+        self.attribute_predicates.extend(attribute_predicates);
+        Ok(())
+    }
+
+    /// Build a checked predicate expression from the registered predicates.
+    /// Returns the name of the first predicate which is not registered.
+    fn build_predicate(&self, expr: &PredicateExpr) -> Result<DynamicPredicate<'p, P>, String> {
+        let build_all = |args: &[PredicateExpr]| {
+            args.iter()
+                .map(|arg| self.build_predicate(arg))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(match *expr {
+            PredicateExpr::Name(ref name) => match self.predicates.get(name) {
+                Some(predicate) => DynamicPredicate::Registered(*predicate),
+                None => return Err(name.clone()),
+            },
+            PredicateExpr::Call(ref name, ref args) => match (name.as_str(), args.as_slice()) {
+                ("all", _) => DynamicPredicate::All(build_all(args)?),
+                ("any", _) => DynamicPredicate::Any(build_all(args)?),
+                ("not", [arg]) => {
+                    DynamicPredicate::Not(Box::new(self.build_predicate(arg)?), arg.to_string())
+                }
+                ("max_length", [PredicateExpr::Integer(limit)]) => {
+                    DynamicPredicate::MaxLength(*limit as usize)
+                }
+                _ => unreachable!("predicate expressions are checked by check_spec"),
+            },
+            PredicateExpr::Integer(_) => {
+                unreachable!("predicate expressions are checked by check_spec")
+            }
+        })
+    }
+
+    /// All templates of this registry.
+    pub fn templates(&self) -> &[SpecTemplate] {
+        &self.templates
+    }
+
+    /// Get the specification of a template by one of its names.
+    pub fn spec_of(&self, name: &str) -> Option<&SpecTemplate> {
+        let name = name.trim().to_lowercase();
+        self.templates
+            .iter()
+            .find(|t| t.names.iter().any(|n| n.trim().to_lowercase() == name))
+    }
+
+    /// Get the predicate of a template attribute.
+    pub fn predicate(&self, template: &str, attribute: &str) -> Option<&DynamicPredicate<'p, P>> {
+        self.attribute_predicates
+            .get(&(template.to_string(), attribute.to_string()))
+    }
+
+    /// Try to recognize a template element, using the specification.
+    /// Returns `None` for unknown templates or if required attributes are missing.
+    pub fn parse_template<'e>(&self, template: &'e Template) -> Option<DynamicTemplate<'e, '_>> {
+        let spec = self.spec_of(&extract_plain_text(&template.name))?;
+        let mut present = vec![];
+
+        for attribute in &spec.attributes {
+            let names: Vec<String> = attribute
+                .names
+                .iter()
+                .map(|n| n.trim().to_lowercase())
+                .collect();
+
+            let args = if attribute.repeat {
+                repeated_args(&template.content, &names)
+            } else {
+                let positional: Vec<String> =
+                    attribute.position.iter().map(|p| p.to_string()).collect();
+                let arg = find_arg(&template.content, &names)
+                    .or_else(|| find_arg(&template.content, &positional));
+                match arg {
+                    Some(Element::TemplateArgument(arg)) => vec![arg],
+                    _ => vec![],
+                }
+            };
+
+            if args.is_empty() && attribute.priority == SpecPriority::Required {
+                return None;
+            }
+            for arg in args {
+                present.push(DynamicAttribute {
+                    name: attribute.identifier.clone(),
+                    priority: attribute.priority,
+                    value: &arg.value,
+                    position: arg.position.clone(),
+                });
+            }
+        }
+        Some(DynamicTemplate { spec, present })
+    }
+}
+
+/// Arguments of a repeated attribute (`item1`, `item2`, ...), ordered by their number.
+fn repeated_args<'e>(content: &'e [Element], prefixes: &[String]) -> Vec<&'e TemplateArgument> {
+    let mut items = vec![];
+    for child in content {
+        if let Element::TemplateArgument(ref arg) = *child {
+            let name = arg.name.trim().to_lowercase();
+            let index = prefixes
+                .iter()
+                .filter(|prefix| name.starts_with(prefix.as_str()))
+                .map(|prefix| &name[prefix.len()..])
+                .filter(|suffix| !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit()))
+                .filter_map(|suffix| suffix.parse::<usize>().ok())
+                .next();
+            if let Some(index) = index {
+                // MediaWiki uses the last of duplicate arguments.
+                items.retain(|&(other, _)| other != index);
+                items.push((index, arg));
+            }
+        }
+    }
+    items.sort_by_key(|&(index, _)| index);
+    items.into_iter().map(|(_, arg)| arg).collect()
+}
+
+impl<'p, P: ?Sized> DynamicPredicate<'p, P> {
+    /// Check `content`, like the generated predicate of the `template_spec!` macro.
+    /// `call` evaluates a registered predicate, e.g.
+    /// `|p, c| p(c).map_err(|e| DynamicPredError { tree: e.tree, cause: e.cause })`.
+    pub fn check<'e, C>(&self, content: &'e [Element], call: &C) -> Result<(), DynamicPredError<'e>>
+    where
+        C: Fn(&P, &'e [Element]) -> Result<(), DynamicPredError<'e>>,
+    {
+        match *self {
+            DynamicPredicate::Registered(predicate) => call(predicate, content),
+            DynamicPredicate::All(ref predicates) => predicates
+                .iter()
+                .try_for_each(|predicate| predicate.check(content, call)),
+            DynamicPredicate::Any(ref predicates) => {
+                let mut error: Option<DynamicPredError> = None;
+                for predicate in predicates {
+                    let other = match predicate.check(content, call) {
+                        Ok(()) => return Ok(()),
+                        Err(other) => other,
+                    };
+                    error = Some(match error {
+                        Some(first) => DynamicPredError {
+                            tree: first.tree,
+                            cause: format!("{} Or: {}", first.cause, other.cause),
+                        },
+                        None => other,
+                    });
+                }
+                error.map_or(Ok(()), Err)
+            }
+            DynamicPredicate::Not(ref predicate, ref expr) => {
+                match predicate.check(content, call) {
+                    Ok(()) => Err(DynamicPredError {
+                        tree: content.first(),
+                        cause: format!("{} must not apply here!", expr),
+                    }),
+                    Err(_) => Ok(()),
+                }
+            }
+            DynamicPredicate::MaxLength(limit) => {
+                let length = extract_plain_text(content).trim().chars().count();
+                if length > limit {
+                    return Err(DynamicPredError {
+                        tree: content.first(),
+                        cause: format!(
+                            "text must not be longer than {} characters, found {}!",
+                            limit, length
+                        ),
+                    });
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<'e, 's> DynamicTemplate<'e, 's> {
+    pub fn identifier(&self) -> &str {
+        &self.spec.identifier
+    }
+    pub fn description(&self) -> &str {
+        &self.spec.description
+    }
+    pub fn names(&self) -> &Vec<String> {
+        &self.spec.names
+    }
+    pub fn format(&self) -> SpecFormat {
+        self.spec.format
+    }
+    pub fn present(&self) -> &Vec<DynamicAttribute<'e>> {
+        &self.present
+    }
+    pub fn find(&self, name: &str) -> Option<&DynamicAttribute<'e>> {
+        self.present.iter().find(|attribute| attribute.name == name)
+    }
+}
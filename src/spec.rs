@@ -0,0 +1,6 @@
+//! The template specification format, as read by `SpecRegistry` and produced by `templatedata`.
+
+pub use mwparser_utils_spec::{
+    SpecAttribute, SpecDefault, SpecDeprecation, SpecError, SpecFormat, SpecPriority, SpecSeverity,
+    SpecTemplate, SpecType,
+};
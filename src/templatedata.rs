@@ -0,0 +1,453 @@
+//! Import of MediaWiki TemplateData descriptions into draft template specifications.
+
+use mwparser_utils_spec::{
+    SpecAttribute, SpecDefault, SpecDeprecation, SpecFormat, SpecPriority, SpecTemplate, SpecType,
+};
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Languages to prefer for localized texts, in order.
+const LANGUAGES: [&str; 2] = ["de", "en"];
+
+const KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+/// A TemplateData feature which has no equivalent in the specification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportWarning {
+    pub template: String,
+    pub parameter: Option<String>,
+    pub message: String,
+}
+
+/// Draft specification created from TemplateData.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportResult {
+    pub templates: Vec<SpecTemplate>,
+    pub warnings: Vec<ImportWarning>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum InterfaceText {
+    Plain(String),
+    Localized(BTreeMap<String, String>),
+}
+
+#[derive(Debug, Deserialize)]
+struct TemplateData {
+    #[serde(default)]
+    description: Option<InterfaceText>,
+    #[serde(default)]
+    params: BTreeMap<String, TemplateDataParam>,
+    #[serde(rename = "paramOrder", default)]
+    param_order: Vec<String>,
+    #[serde(default)]
+    format: Option<String>,
+    #[serde(default)]
+    sets: Vec<serde_json::Value>,
+    #[serde(default)]
+    maps: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TemplateDataParam {
+    #[serde(default)]
+    description: Option<InterfaceText>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    required: bool,
+    #[serde(default)]
+    suggested: bool,
+    #[serde(default)]
+    deprecated: Option<serde_json::Value>,
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
+    #[serde(default)]
+    default: Option<InterfaceText>,
+    #[serde(rename = "suggestedvalues", default)]
+    suggested_values: Vec<String>,
+    #[serde(default)]
+    inherits: Option<String>,
+    #[serde(default)]
+    autovalue: Option<String>,
+}
+
+impl InterfaceText {
+    fn text(&self) -> String {
+        match *self {
+            InterfaceText::Plain(ref text) => text.clone(),
+            InterfaceText::Localized(ref texts) => LANGUAGES
+                .iter()
+                .filter_map(|lang| texts.get(*lang))
+                .chain(texts.values())
+                .next()
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+struct Importer<'a> {
+    template: &'a str,
+    warnings: Vec<ImportWarning>,
+}
+
+impl<'a> Importer<'a> {
+    fn warn(&mut self, parameter: Option<&str>, message: String) {
+        self.warnings.push(ImportWarning {
+            template: self.template.into(),
+            parameter: parameter.map(String::from),
+            message,
+        });
+    }
+}
+
+/// Create a template identifier from a template name (`beispiel satz` -> `BeispielSatz`).
+fn template_identifier(name: &str) -> String {
+    let mut identifier = String::new();
+    for word in name.split(|c: char| !c.is_alphanumeric()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            identifier.extend(first.to_uppercase());
+            identifier.extend(chars);
+        }
+    }
+    if !identifier.starts_with(char::is_alphabetic) {
+        identifier.insert(0, 'T');
+    }
+    identifier
+}
+
+/// Create an attribute identifier from a parameter name.
+fn attribute_identifier(name: &str) -> String {
+    let mut identifier: String = name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    if !identifier.starts_with(char::is_alphabetic) {
+        identifier.insert_str(0, "arg");
+    }
+    if KEYWORDS.contains(&identifier.as_str()) {
+        identifier.push('_');
+    }
+    identifier
+}
+
+fn import_param(
+    importer: &mut Importer,
+    key: &str,
+    param: &TemplateDataParam,
+    predicate: &str,
+) -> SpecAttribute {
+    // numbered parameters become positional if they have named aliases.
+    let number = key.trim().parse::<usize>().ok();
+    let (names, position) = match number {
+        Some(number) if !param.aliases.is_empty() => (param.aliases.clone(), Some(number)),
+        _ => {
+            let mut names = vec![key.to_string()];
+            names.extend(param.aliases.iter().cloned());
+            (names, None)
+        }
+    };
+
+    let identifier = attribute_identifier(&names[0]);
+    if identifier != names[0].trim().to_lowercase() {
+        importer.warn(Some(key), format!("renamed to identifier {:?}", identifier));
+    }
+
+    let mut kind = match param.kind.as_deref() {
+        None | Some("unknown") => None,
+        Some("number") => Some(SpecType::Integer),
+        Some("boolean") => Some(SpecType::Boolean),
+        Some("line") => Some(SpecType::Text),
+        Some("content") | Some("unbalanced-wikitext") | Some("string") => Some(SpecType::Wikitext),
+        Some("wiki-file-name") => Some(SpecType::File),
+        Some(other) => {
+            importer.warn(Some(key), format!("type {:?} is not supported", other));
+            None
+        }
+    };
+    if !param.suggested_values.is_empty() {
+        importer.warn(
+            Some(key),
+            "suggested values are imported as enumeration of allowed values".into(),
+        );
+        kind = Some(SpecType::Enum(param.suggested_values.clone()));
+    }
+
+    let default = param.default.as_ref().map(|default| match kind {
+        Some(SpecType::Text)
+        | Some(SpecType::Integer)
+        | Some(SpecType::Boolean)
+        | Some(SpecType::Enum(_)) => SpecDefault::Text(default.text()),
+        _ => SpecDefault::Wikitext(default.text()),
+    });
+    if default.is_some() && param.required {
+        importer.warn(
+            Some(key),
+            "default value of required parameter is dropped".into(),
+        );
+    }
+
+    let deprecated = match param.deprecated {
+        None | Some(serde_json::Value::Bool(false)) => None,
+        Some(serde_json::Value::String(ref message)) => Some(SpecDeprecation {
+            message: message.clone(),
+            replacement: None,
+        }),
+        Some(_) => Some(SpecDeprecation {
+            message: "This parameter is deprecated.".into(),
+            replacement: None,
+        }),
+    };
+    if let Some(ref inherits) = param.inherits {
+        importer.warn(
+            Some(key),
+            format!("inheritance from {:?} is not supported", inherits),
+        );
+    }
+    if param.autovalue.is_some() {
+        importer.warn(Some(key), "autovalue is not supported".into());
+    }
+
+    SpecAttribute {
+        identifier,
+        description: param
+            .description
+            .as_ref()
+            .map(InterfaceText::text)
+            .unwrap_or_default(),
+        names,
+        priority: if param.required {
+            SpecPriority::Required
+        } else if param.suggested {
+            SpecPriority::Recommended
+        } else {
+            SpecPriority::Optional
+        },
+        predicate: predicate.into(),
+        context_predicate: None,
+        severity: None,
+        kind,
+        default: if param.required { None } else { default },
+        position,
+        repeat: false,
+        deprecated,
+        deprecated_names: BTreeMap::new(),
+        requires: vec![],
+        conflicts_with: vec![],
+    }
+}
+
+/// Create a draft specification of a template from its TemplateData JSON.
+/// Attributes use `predicate` as predicate name.
+pub fn import_template(
+    name: &str,
+    source: &str,
+    predicate: &str,
+) -> Result<(SpecTemplate, Vec<ImportWarning>), serde_json::Error> {
+    let data: TemplateData = serde_json::from_str(source)?;
+    let identifier = template_identifier(name);
+    let mut importer = Importer {
+        template: &identifier,
+        warnings: vec![],
+    };
+
+    let format = match data.format.as_deref() {
+        None | Some("inline") => SpecFormat::Inline,
+        Some("block") => SpecFormat::Block,
+        Some(other) => {
+            importer.warn(
+                None,
+                format!("custom format {:?} is imported as block", other),
+            );
+            SpecFormat::Block
+        }
+    };
+    if !data.sets.is_empty() {
+        importer.warn(None, "parameter sets are not supported".into());
+    }
+    if data.maps.is_some() {
+        importer.warn(None, "maps are not supported".into());
+    }
+
+    let mut keys: Vec<&String> = data
+        .param_order
+        .iter()
+        .filter(|key| data.params.contains_key(*key))
+        .collect();
+    keys.extend(
+        data.params
+            .keys()
+            .filter(|key| !data.param_order.contains(key)),
+    );
+
+    let mut attributes: Vec<SpecAttribute> = vec![];
+    for key in keys {
+        let mut attribute = import_param(&mut importer, key, &data.params[key], predicate);
+        while attributes
+            .iter()
+            .any(|a| a.identifier == attribute.identifier)
+        {
+            attribute.identifier.push('_');
+            importer.warn(
+                Some(key),
+                format!("renamed to identifier {:?}", attribute.identifier),
+            );
+        }
+        attributes.push(attribute);
+    }
+
+    let template = SpecTemplate {
+        identifier: identifier.clone(),
+        description: data
+            .description
+            .as_ref()
+            .map(InterfaceText::text)
+            .unwrap_or_default(),
+        names: vec![name.trim().replace('_', " ").to_lowercase()],
+        attributes,
+        format,
+        deprecated: None,
+        deprecated_names: BTreeMap::new(),
+        one_of: vec![],
+        allowed_parents: None,
+        allowed_children: None,
+        extends: None,
+        attribute_groups: vec![],
+    };
+    Ok((template, importer.warnings))
+}
+
+/// Import all TemplateData files (`<template name>.json`) of a directory.
+pub fn import_dir<P: AsRef<Path>>(path: P, predicate: &str) -> io::Result<ImportResult> {
+    let mut entries: Vec<_> = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map(|e| e == "json").unwrap_or(false))
+        .collect();
+    entries.sort();
+
+    let mut result = ImportResult {
+        templates: vec![],
+        warnings: vec![],
+    };
+    for path in entries {
+        let name = match path.file_stem() {
+            Some(stem) => stem.to_string_lossy().into_owned(),
+            None => continue,
+        };
+        let source = fs::read_to_string(&path)?;
+        match import_template(&name, &source, predicate) {
+            Ok((template, mut warnings)) => {
+                result.templates.push(template);
+                result.warnings.append(&mut warnings);
+            }
+            Err(error) => result.warnings.push(ImportWarning {
+                template: template_identifier(&name),
+                parameter: None,
+                message: format!("cannot parse {:?}: {}", path, error),
+            }),
+        }
+    }
+    Ok(result)
+}
+
+fn shares_name(a: &[String], b: &[String]) -> bool {
+    a.iter().any(|n| {
+        b.iter()
+            .any(|m| n.trim().to_lowercase() == m.trim().to_lowercase())
+    })
+}
+
+/// Update an existing specification with imported drafts.
+///
+/// Templates and attributes are matched by their names. Names, descriptions and
+/// priorities are taken from the drafts, while predicates, types and defaults
+/// of existing attributes are kept. Unknown templates and attributes are added,
+/// attributes missing from a draft are reported.
+pub fn resync(spec: &[SpecTemplate], drafts: &[SpecTemplate]) -> ImportResult {
+    let mut templates = spec.to_vec();
+    let mut warnings = vec![];
+
+    for draft in drafts {
+        let existing = match templates
+            .iter_mut()
+            .find(|t| shares_name(&t.names, &draft.names))
+        {
+            Some(existing) => existing,
+            None => {
+                templates.push(draft.clone());
+                continue;
+            }
+        };
+        if !draft.description.is_empty() {
+            existing.description = draft.description.clone();
+        }
+        for name in &draft.names {
+            if !shares_name(&existing.names, std::slice::from_ref(name)) {
+                existing.names.push(name.clone());
+            }
+        }
+
+        for attribute in &existing.attributes {
+            if !draft
+                .attributes
+                .iter()
+                .any(|a| shares_name(&a.names, &attribute.names))
+            {
+                warnings.push(ImportWarning {
+                    template: existing.identifier.clone(),
+                    parameter: Some(attribute.identifier.clone()),
+                    message: "attribute is not part of the TemplateData".into(),
+                });
+            }
+        }
+        for new in &draft.attributes {
+            match existing
+                .attributes
+                .iter_mut()
+                .find(|a| shares_name(&a.names, &new.names))
+            {
+                Some(attribute) => {
+                    for name in &new.names {
+                        if !shares_name(&attribute.names, std::slice::from_ref(name)) {
+                            attribute.names.push(name.clone());
+                        }
+                    }
+                    if !new.description.is_empty() {
+                        attribute.description = new.description.clone();
+                    }
+                    attribute.priority = new.priority;
+                    if attribute.priority == SpecPriority::Required {
+                        attribute.default = None;
+                    }
+                    if new.deprecated.is_some() && attribute.deprecated.is_none() {
+                        attribute.deprecated = new.deprecated.clone();
+                    }
+                }
+                None => existing.attributes.push(new.clone()),
+            }
+        }
+    }
+    ImportResult {
+        templates,
+        warnings,
+    }
+}
+
+/// Render a (draft) specification as YAML.
+pub fn to_yaml(templates: &[SpecTemplate]) -> Result<String, serde_yaml::Error> {
+    serde_yaml::to_string(templates)
+}
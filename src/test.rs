@@ -1,10 +1,1277 @@
-use crate::util::{extract_plain_text, find_arg};
+use crate::registry::{DynamicPredError, DynamicPredicate, RegistryError, SpecRegistry};
+use crate::spec::{SpecFormat, SpecPriority, SpecTemplate};
+use crate::templatedata::import_template;
+use crate::transformations::{canonicalize_templates, migrate_deprecated};
+use crate::util::{extract_plain_text, find_arg, to_wikitext};
+use mediawiki_parser::MarkupType;
 use mwparser_utils_derive::template_spec;
-
-const _SPEC: &str = include_str!("test_spec.yml");
+use mwparser_utils_spec::{
+    check_spec, load_files, merge_files, parse_predicate, LoadedSpec, SpecFile,
+};
+use serde_json::json;
+use std::borrow::Cow;
 
 fn nop_pred<'s>(_: &'s [Element]) -> PredResult<'s> {
     Ok(())
 }
 
+/// The title of an example must not repeat the heading of its section.
+fn title_not_heading<'e>(content: &'e [Element], context: &PredContext) -> PredResult<'e> {
+    let heading = context
+        .path
+        .iter()
+        .rev()
+        .find_map(|element| match **element {
+            Element::Heading(ref heading) => Some(heading),
+            _ => None,
+        });
+    match heading {
+        Some(heading)
+            if extract_plain_text(&heading.caption).trim()
+                == extract_plain_text(content).trim() =>
+        {
+            Err(PredError {
+                tree: content.first(),
+                cause: "the title must not repeat the section heading!".into(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
 template_spec!("src/test_spec.yml");
+
+const SPEC: &str = include_str!("test_spec.yml");
+
+fn spec_templates() -> Vec<SpecTemplate> {
+    SpecFile::from_yaml(SPEC).unwrap().resolve().unwrap()
+}
+
+fn resolve_errors(source: &str) -> Vec<String> {
+    match SpecFile::from_yaml(source).unwrap().resolve() {
+        Ok(_) => vec![],
+        Err(errors) => errors.iter().map(|e| e.to_string()).collect(),
+    }
+}
+
+fn parse_content(source: &str) -> Vec<Element> {
+    match mediawiki_parser::parse(source).unwrap() {
+        Element::Document(document) => document.content,
+        other => panic!("not a document: {:?}", other),
+    }
+}
+
+/// The first template of the source text.
+fn parse_first_template(source: &str) -> Template {
+    let content = parse_content(source);
+    let template = match content.first() {
+        Some(Element::Paragraph(paragraph)) => paragraph.content.first(),
+        other => other,
+    };
+    match template {
+        Some(Element::Template(template)) => template.clone(),
+        other => panic!("not a template: {:?}", other),
+    }
+}
+
+#[test]
+fn wikitext_roundtrip() {
+    let source = "== Heading ==\ntext '''bold''' <math>x^2</math> {{tpl|a|b=c}}\n\n* one\n*# two\n";
+    let wikitext = to_wikitext(&parse_content(source));
+    assert_eq!(to_wikitext(&parse_content(&wikitext)), wikitext);
+
+    let template = parse_first_template("{{liste|item2=b|type=ol|item1=a}}");
+    let known = parse_template(&template).unwrap();
+    assert_eq!(known.to_wikitext(), "{{list|type=ol|item1=a|item2=b}}");
+    let template = parse_first_template(&known.to_wikitext());
+    let reparsed = parse_template(&template).unwrap();
+    assert_eq!(reparsed.to_wikitext(), known.to_wikitext());
+    match reparsed {
+        KnownTemplate::List(list) => {
+            let items: Vec<String> = list.items.iter().map(|i| extract_plain_text(i)).collect();
+            assert_eq!(items, vec!["a", "b"]);
+        }
+        other => panic!("not a list: {:?}", other),
+    }
+
+    // the default name is normalized like in `spec_of`.
+    let template = parse_first_template("{{example|example=x|title=T}}");
+    let known = parse_template(&template).unwrap();
+    assert_eq!(known.to_wikitext(), "{{example|title=T|example=x}}");
+    assert_eq!(spec_of("Example").unwrap().default_name(), "example");
+}
+
+#[test]
+fn validate_runs_predicates() {
+    let source = "{{example|example=x|title=a ''b''}}";
+    let template = parse_first_template(source);
+    let violations = validate_template(&parse_template(&template).unwrap());
+    assert_eq!(violations.len(), 1, "{:?}", violations);
+    let violation = &violations[0];
+    assert_eq!(
+        (&violation.template[..], &violation.attribute[..]),
+        ("Example", "title")
+    );
+    assert_eq!(
+        violation.predicate_name,
+        "all(builtin::plain_text_only, max_length(80))"
+    );
+    // the element given by the predicate, not the whole argument.
+    let span = &violation.position;
+    assert_eq!(&source[span.start.offset..span.end.offset], "''b''");
+    assert_eq!(validate_raw_template(&template), Some(violations));
+
+    // without an element, the whole argument is reported.
+    let source = "{{example|example= |title=a}}";
+    let template = parse_first_template(source);
+    let violations = validate_raw_template(&template).unwrap();
+    assert_eq!(violations.len(), 1, "{:?}", violations);
+    assert_eq!(violations[0].attribute, "example");
+    let span = &violations[0].position;
+    assert_eq!(&source[span.start.offset..span.end.offset], "example= ");
+
+    let valid = parse_first_template("{{example|example=x|title=a}}");
+    assert_eq!(validate_raw_template(&valid), Some(vec![]));
+    assert_eq!(
+        validate_raw_template(&parse_first_template("{{unknown}}")),
+        None
+    );
+}
+
+#[test]
+fn argument_issues() {
+    let template = parse_first_template("{{list|item1=a|foo=x|itemx=y|item1=b|item3=c}}");
+    let issues = check_arguments(&template).unwrap();
+    let kinds: Vec<&str> = issues
+        .iter()
+        .map(|issue| match issue {
+            ArgumentIssue::Unknown { name, .. } => {
+                assert_eq!(name, "foo");
+                "unknown"
+            }
+            ArgumentIssue::Duplicate {
+                name, positions, ..
+            } => {
+                assert_eq!((name.as_str(), positions.len()), ("item1", 2));
+                "duplicate"
+            }
+            ArgumentIssue::InvalidRepeatSuffix { name, .. } => {
+                assert_eq!(name, "itemx");
+                "suffix"
+            }
+            ArgumentIssue::RepeatGap { missing, .. } => {
+                assert_eq!(missing, &vec![2]);
+                "gap"
+            }
+            other => panic!("unexpected issue: {:?}", other),
+        })
+        .collect();
+    assert_eq!(kinds.len(), 4);
+    for kind in &["unknown", "duplicate", "suffix", "gap"] {
+        assert!(kinds.contains(kind), "{} missing in {:?}", kind, issues);
+    }
+
+    let template = parse_first_template("{{list|item1=a|item2=b}}");
+    assert_eq!(check_arguments(&template), Some(vec![]));
+    assert_eq!(
+        check_arguments(&parse_first_template("{{unknown|a}}")),
+        None
+    );
+}
+
+#[test]
+fn partial_templates() {
+    let template = parse_first_template("{{example|title=y}}");
+    assert_eq!(parse_template(&template), None);
+    match parse_template_partial(&template) {
+        Some(Err(incomplete)) => {
+            assert_eq!(incomplete.identifier, "Example");
+            assert_eq!(incomplete.missing, vec!["example"]);
+            let present: Vec<&str> = incomplete.present.iter().map(|a| &a.name[..]).collect();
+            assert_eq!(present, vec!["title"]);
+        }
+        other => panic!("not incomplete: {:?}", other),
+    }
+    let violations: Vec<(String, String, Severity)> = validate_raw_template(&template)
+        .unwrap()
+        .into_iter()
+        .map(|v| (v.attribute, v.predicate_name, v.severity))
+        .collect();
+    assert_eq!(
+        violations,
+        vec![("example".into(), "required".into(), Severity::Error)]
+    );
+
+    let complete = parse_first_template("{{example|example=x}}");
+    assert!(matches!(parse_template_partial(&complete), Some(Ok(_))));
+    assert_eq!(
+        parse_template_partial(&parse_first_template("{{unknown}}")),
+        None
+    );
+}
+
+/// The source text covered by a span.
+fn source_of<'s>(source: &'s str, span: &Span) -> &'s str {
+    &source[span.start.offset..span.end.offset]
+}
+
+#[test]
+fn typed_values() {
+    let source = "{{figure|file=Datei: a.png |width= 200|border=Ja}}";
+    let template = parse_first_template(source);
+    let figure = match parse_template(&template) {
+        Some(KnownTemplate::Figure(figure)) => figure,
+        other => panic!("not a figure: {:?}", other),
+    };
+    assert_eq!(figure.file_value(), Ok("a.png".to_string()));
+    assert_eq!(figure.width_value(), Some(Ok(200)));
+    assert_eq!(figure.border_value(), Some(Ok(true)));
+
+    let template = parse_first_template("{{list|type=OL|item1=a|item2=b}}");
+    let list = match parse_template(&template) {
+        Some(KnownTemplate::List(list)) => list,
+        other => panic!("not a list: {:?}", other),
+    };
+    assert_eq!(list.kind_value(), Some(Ok("ol".to_string())));
+
+    let template = parse_first_template("{{example|example=''x''|title= T }}");
+    let example = match parse_template(&template) {
+        Some(KnownTemplate::Example(example)) => example,
+        other => panic!("not an example: {:?}", other),
+    };
+    assert_eq!(example.title_value(), Some(Ok("T".to_string())));
+    assert_eq!(example.example_value(), Ok(example.example));
+
+    let template = parse_first_template("{{equation|formula= <math>x^2</math> }}");
+    let equation = match parse_template(&template) {
+        Some(KnownTemplate::Equation(equation)) => equation,
+        other => panic!("not an equation: {:?}", other),
+    };
+    assert_eq!(
+        equation.formula_value().map(|f| f.markup),
+        Ok(MarkupType::Math)
+    );
+}
+
+#[test]
+fn conversion_errors() {
+    let source = "{{figure|file=File: |width=wide|border=maybe}}";
+    let template = parse_first_template(source);
+    let figure = match parse_template(&template) {
+        Some(KnownTemplate::Figure(figure)) => figure,
+        other => panic!("not a figure: {:?}", other),
+    };
+    let error = figure.file_value().unwrap_err();
+    assert_eq!(error.attribute, "file");
+    assert_eq!(source_of(source, &error.position), "File: ");
+    let error = figure.width_value().unwrap().unwrap_err();
+    assert_eq!(error.attribute, "width");
+    assert_eq!(source_of(source, &error.position), "wide");
+    let error = figure.border_value().unwrap().unwrap_err();
+    assert_eq!(source_of(source, &error.position), "maybe");
+
+    // markup is reported at the offending element.
+    let source = "{{figure|file=a ''b''.png}}";
+    let template = parse_first_template(source);
+    let figure = match parse_template(&template) {
+        Some(KnownTemplate::Figure(figure)) => figure,
+        other => panic!("not a figure: {:?}", other),
+    };
+    let error = figure.file_value().unwrap_err();
+    assert_eq!(source_of(source, &error.position), "''b''");
+
+    let source = "{{list|type=dl|item1=a}}";
+    let template = parse_first_template(source);
+    let list = match parse_template(&template) {
+        Some(KnownTemplate::List(list)) => list,
+        other => panic!("not a list: {:?}", other),
+    };
+    let error = list.kind_value().unwrap().unwrap_err();
+    assert_eq!(
+        (error.attribute.as_str(), source_of(source, &error.position)),
+        ("kind", "dl")
+    );
+
+    let source = "{{equation|formula=<math>a</math> <math>b</math>}}";
+    let template = parse_first_template(source);
+    let equation = match parse_template(&template) {
+        Some(KnownTemplate::Equation(equation)) => equation,
+        other => panic!("not an equation: {:?}", other),
+    };
+    let error = equation.formula_value().unwrap_err();
+    assert_eq!(
+        source_of(source, &error.position),
+        "<math>a</math> <math>b</math>"
+    );
+}
+
+#[test]
+fn default_values() {
+    let template = parse_first_template("{{list|item1=a}}");
+    let list = match parse_template(&template) {
+        Some(KnownTemplate::List(list)) => list,
+        other => panic!("not a list: {:?}", other),
+    };
+    let kind = list.kind_or_default();
+    assert!(matches!(kind, Cow::Owned(_)));
+    assert_eq!(extract_plain_text(&kind), "ul");
+
+    let template = parse_first_template("{{list|type=ol|item1=a}}");
+    let list = match parse_template(&template) {
+        Some(KnownTemplate::List(list)) => list,
+        other => panic!("not a list: {:?}", other),
+    };
+    let kind = list.kind_or_default();
+    assert!(matches!(kind, Cow::Borrowed(_)));
+    assert_eq!(extract_plain_text(&kind), "ol");
+
+    // wikitext defaults are parsed, without a surrounding paragraph.
+    let template = parse_first_template("{{figure|file=a.png}}");
+    let figure = match parse_template(&template) {
+        Some(KnownTemplate::Figure(figure)) => figure,
+        other => panic!("not a figure: {:?}", other),
+    };
+    let caption = figure.caption_or_default();
+    match &caption[..] {
+        [Element::Formatted(formatted)] => {
+            assert_eq!(formatted.markup, MarkupType::Italic);
+            assert_eq!(extract_plain_text(&formatted.content), "No caption.");
+        }
+        other => panic!("not italic text: {:?}", other),
+    }
+
+    let spec = spec_of("Figure").unwrap();
+    let caption = spec.attributes.iter().find(|a| a.identifier == "caption");
+    assert_eq!(
+        caption.unwrap().default,
+        Some(DefaultValue::Wikitext("''No caption.''".into()))
+    );
+    let file = spec.attributes.iter().find(|a| a.identifier == "file");
+    assert_eq!(file.unwrap().default, None);
+}
+
+#[test]
+fn positional_arguments() {
+    let template = parse_first_template("{{figure| a.png |width=10}}");
+    let figure = match parse_template(&template) {
+        Some(KnownTemplate::Figure(figure)) => figure,
+        other => panic!("not a figure: {:?}", other),
+    };
+    assert_eq!(figure.file_value(), Ok("a.png".to_string()));
+    assert_eq!(check_arguments(&template), Some(vec![]));
+    let file = spec_of("Figure").unwrap().attributes.remove(0);
+    assert_eq!((file.identifier.as_str(), file.position), ("file", Some(1)));
+
+    // the named argument is used if both are given.
+    let source = "{{figure|a.png|file=b.png}}";
+    let template = parse_first_template(source);
+    let figure = match parse_template(&template) {
+        Some(KnownTemplate::Figure(figure)) => figure,
+        other => panic!("not a figure: {:?}", other),
+    };
+    assert_eq!(figure.file_value(), Ok("b.png".to_string()));
+    match &check_arguments(&template).unwrap()[..] {
+        [ArgumentIssue::NamedAndPositional {
+            attribute,
+            names,
+            positions,
+        }] => {
+            assert_eq!(attribute, "file");
+            assert_eq!(names, &vec!["1".to_string(), "file".to_string()]);
+            let given: Vec<&str> = positions.iter().map(|p| source_of(source, p)).collect();
+            assert_eq!(given, vec!["a.png", "file=b.png"]);
+        }
+        other => panic!("expected a named and positional issue: {:?}", other),
+    }
+
+    // further positional arguments are not part of the specification.
+    let template = parse_first_template("{{figure|a.png|b}}");
+    match &check_arguments(&template).unwrap()[..] {
+        [ArgumentIssue::Unknown { name, .. }] => assert_eq!(name, "2"),
+        other => panic!("expected an unknown argument: {:?}", other),
+    }
+}
+
+#[test]
+fn registry() {
+    let mut registry: SpecRegistry<Predicate> = SpecRegistry::new();
+    match registry.load_str(SPEC) {
+        Err(RegistryError::UnknownPredicate { predicate, .. }) => {
+            assert!(predicate.starts_with("builtin::") || predicate == "nop_pred")
+        }
+        other => panic!("expected an unknown predicate: {:?}", other),
+    }
+    assert!(registry.templates().is_empty());
+    registry.register_predicate("nop_pred", &nop_pred);
+    for (name, predicate) in spec_meta::predicates::builtins() {
+        registry.register_predicate(name, predicate);
+    }
+    registry.load_str(SPEC).unwrap();
+    assert_eq!(registry.templates().len(), spec().len());
+    assert_eq!(registry.spec_of(" Liste").unwrap().identifier, "List");
+    // templates can only be defined once.
+    assert!(matches!(
+        registry.load_str(SPEC),
+        Err(RegistryError::Spec(_))
+    ));
+
+    let template = parse_first_template("{{liste|item2=b|type=ol|item1=a}}");
+    let list = registry.parse_template(&template).unwrap();
+    assert_eq!(list.identifier(), "List");
+    let present: Vec<(&str, String)> = list
+        .present()
+        .iter()
+        .map(|a| (a.name.as_str(), extract_plain_text(a.value)))
+        .collect();
+    assert_eq!(
+        present,
+        vec![
+            ("kind", "ol".to_string()),
+            ("items", "a".to_string()),
+            ("items", "b".to_string())
+        ]
+    );
+    assert_eq!(extract_plain_text(list.find("kind").unwrap().value), "ol");
+    assert_eq!(list.find("missing"), None);
+
+    // like the generated parser, missing required attributes are not recognized.
+    let template = parse_first_template("{{figure|a.png}}");
+    let figure = registry.parse_template(&template).unwrap();
+    assert_eq!(
+        extract_plain_text(figure.find("file").unwrap().value),
+        "a.png"
+    );
+    assert_eq!(
+        registry.parse_template(&parse_first_template("{{figure|width=1}}")),
+        None
+    );
+    assert_eq!(
+        registry.parse_template(&parse_first_template("{{unknown}}")),
+        None
+    );
+}
+
+#[test]
+fn template_data() {
+    let data = serde_json::to_value(Figure::template_data()).unwrap();
+    assert_eq!(data["format"], "block");
+    assert_eq!(
+        data["paramOrder"],
+        json!(["file", "width", "border", "caption"])
+    );
+    assert_eq!(
+        data["params"]["file"],
+        json!({
+            "label": "file",
+            "description": "The image file.",
+            "aliases": ["1"],
+            "required": true,
+            "suggested": false,
+            "type": "wiki-file-name",
+        })
+    );
+    assert_eq!(data["params"]["caption"]["default"], "''No caption.''");
+
+    let data = serde_json::to_value(spec_of("list").unwrap().template_data()).unwrap();
+    assert_eq!(data["description"], "A list of items.");
+    assert_eq!(data["paramOrder"], json!(["type", "item1"]));
+    let kind = &data["params"]["type"];
+    assert_eq!(
+        kind["suggestedvalues"],
+        json!(["ul", "unordered", "ol", "ordered"])
+    );
+    assert_eq!(
+        (&kind["type"], &kind["default"]),
+        (&json!("line"), &json!("ul"))
+    );
+    assert_eq!(kind.get("aliases"), None);
+    assert_eq!(data["params"]["item1"]["required"], true);
+
+    let data = serde_json::to_value(Example::template_data()).unwrap();
+    assert_eq!(data["params"]["title"]["suggested"], true);
+    assert_eq!(data["params"]["name"]["deprecated"], "Use title instead.");
+}
+
+#[test]
+fn import_templatedata() {
+    let source = r#"{
+        "description": {"en": "A box.", "de": "Eine Box."},
+        "format": "block",
+        "paramOrder": ["title", "1"],
+        "params": {
+            "1": {"aliases": ["content"], "required": true, "description": "Content."},
+            "title": {"suggested": true, "type": "line", "default": "Box"},
+            "old": {"deprecated": "Use title."},
+            "size": {"type": "number"}
+        },
+        "sets": [{"label": "x", "params": ["1"]}]
+    }"#;
+    let (template, warnings) = import_template("Info_Box", source, "nop_pred").unwrap();
+    assert_eq!(template.names, vec!["info box"]);
+    assert_eq!(template.format, SpecFormat::Block);
+    let attributes: Vec<(&str, SpecPriority, &str)> = template
+        .attributes
+        .iter()
+        .map(|a| (&a.identifier[..], a.priority, &a.predicate[..]))
+        .collect();
+    assert_eq!(
+        attributes[0],
+        ("title", SpecPriority::Recommended, "nop_pred")
+    );
+    assert_eq!(attributes[1].1, SpecPriority::Required);
+    assert_eq!(template.attributes[1].position, Some(1));
+    assert!(template.attributes[2].deprecated.is_some());
+    assert!(warnings
+        .iter()
+        .any(|w| w.message == "parameter sets are not supported"));
+    assert!(check_spec(&[template]).is_empty());
+    assert!(import_template("X", "{", "nop_pred").is_err());
+}
+
+#[test]
+fn help_pages() {
+    let list = spec_of("list").unwrap();
+    let usage = "{{list\n| type = \n| item1 = \n}}";
+    assert_eq!(list.usage(), usage);
+    let expected = "\
+== list ==
+
+A list of items.
+
+* Format: Block
+* Alternative names: <code><nowiki>liste</nowiki></code>
+
+{| class=\"wikitable\"
+! Attribute !! Alternative names !! Priority !! Predicate !! Description
+|-
+| <code><nowiki>type</nowiki></code> ||  || Optional || <code><nowiki>nop_pred</nowiki></code> \
+|| The kind of list, ordered or unordered.
+|-
+| <code><nowiki>item1</nowiki></code> ||  || Required || <code><nowiki>nop_pred</nowiki></code> \
+|| The list items, numbered `item1`, `item2`, ...
+|}
+
+=== Usage ===
+
+<pre>
+";
+    assert_eq!(
+        list.help_section(HelpFormat::Wikitext),
+        format!("{}{}\n</pre>\n", expected, usage)
+    );
+    let expected = "\
+# list
+
+A list of items.
+
+* Format: Block
+* Alternative names: `liste`
+
+| Attribute | Alternative names | Priority | Predicate | Description |
+| --- | --- | --- | --- | --- |
+| `type` |  | Optional | `nop_pred` | The kind of list, ordered or unordered. |
+| `item1` |  | Required | `nop_pred` | The list items, numbered `item1`, `item2`, ... |
+
+## Usage
+
+```
+";
+    assert_eq!(
+        list.help_section(HelpFormat::Markdown),
+        format!("{}{}\n```\n", expected, usage)
+    );
+
+    let example = spec_of("Example").unwrap();
+    let section = example.help_section(HelpFormat::Markdown);
+    assert!(section.contains("* `name` cannot be given together with `title`\n"));
+    assert!(section.contains("* Can only contain: `List`\n"));
+    assert!(section.contains(
+        "| `title` |  | Recommended | `all(builtin::plain_text_only, max_length(80))`, \
+         `title_not_heading` | A name for this example. |\n"
+    ));
+    assert!(section.contains("| Deprecated: Use title instead. The former name"));
+    // deprecated attributes are not part of the usage.
+    assert!(section.ends_with("```\n{{example\n| title = \n| example = \n}}\n```\n"));
+
+    let templates = spec();
+    let page = help_page(&templates[..2], HelpFormat::Wikitext);
+    assert!(page.starts_with("== example ==\n"));
+    assert!(page.contains("</pre>\n\n== list ==\n"));
+}
+
+#[test]
+fn canonicalize() {
+    let templates = spec_templates();
+    let document = mediawiki_parser::parse(
+        "a {{ Liste |Item2=b|foo=x|type=ol|item1={{LIST|item1=z}}}} {{Example|example=x|title=y}}",
+    )
+    .unwrap();
+    let (document, renames) = canonicalize_templates(document, &templates, true).unwrap();
+    assert_eq!(
+        to_wikitext(&[document]).trim(),
+        "a {{list|type=ol|item1={{list|item1=z}}|item2=b|foo=x}} {{example|title=y|example=x}}"
+    );
+    let renames: Vec<(&str, Option<&str>, &str, &str)> = renames
+        .iter()
+        .map(|r| {
+            (
+                &r.template[..],
+                r.attribute.as_deref(),
+                &r.from[..],
+                &r.to[..],
+            )
+        })
+        .collect();
+    assert_eq!(
+        renames,
+        vec![
+            ("List", None, "Liste", "list"),
+            ("List", Some("items"), "Item2", "item2"),
+            ("List", None, "LIST", "list"),
+            ("Example", None, "Example", "example"),
+        ]
+    );
+
+    // without reordering, only names are changed.
+    let document = mediawiki_parser::parse("{{list|Item2=b|Type=ol|2=c}}").unwrap();
+    let (document, renames) = canonicalize_templates(document, &templates, false).unwrap();
+    assert_eq!(
+        to_wikitext(&[document]).trim(),
+        "{{list|item2=b|type=ol|2=c}}"
+    );
+    assert_eq!(renames.len(), 2);
+}
+
+#[test]
+fn migrate() {
+    let templates = spec_templates();
+    let document =
+        mediawiki_parser::parse("{{liste|item1=a}} {{example|name=x|example=y}}").unwrap();
+    let (document, renames, conflicts) = migrate_deprecated(document, &templates).unwrap();
+    assert_eq!(
+        to_wikitext(&[document]).trim(),
+        "{{list|item1=a}} {{example|title=x|example=y}}"
+    );
+    let renames: Vec<(&str, &str)> = renames.iter().map(|r| (&r.from[..], &r.to[..])).collect();
+    assert_eq!(renames, vec![("liste", "list"), ("name", "title")]);
+    assert_eq!(conflicts, vec![]);
+
+    // the replacement is already given, renaming would duplicate it.
+    let source = "{{example|name=x|Title=y|example=z}}";
+    let document = mediawiki_parser::parse(source).unwrap();
+    let (document, renames, conflicts) = migrate_deprecated(document, &templates).unwrap();
+    assert_eq!(
+        to_wikitext(&[document]).trim(),
+        "{{example|name=x|Title=y|example=z}}"
+    );
+    assert_eq!(renames, vec![]);
+    match &conflicts[..] {
+        [conflict] => {
+            assert_eq!(conflict.attribute.as_deref(), Some("name"));
+            assert_eq!((&conflict.from[..], &conflict.to[..]), ("name", "title"));
+            let span = &conflict.position;
+            assert_eq!(&source[span.start.offset..span.end.offset], "name=x");
+        }
+        other => panic!("expected one conflict: {:?}", other),
+    }
+}
+
+/// Attribute, constraint and source text of the constraint violations of a template.
+fn constraint_violations(source: &str) -> Vec<(String, String, &str)> {
+    let template = parse_first_template(source);
+    validate_raw_template(&template)
+        .unwrap()
+        .into_iter()
+        .map(|v| {
+            let given = source_of(source, &v.position);
+            (v.attribute, v.predicate_name, given)
+        })
+        .collect()
+}
+
+#[test]
+fn attribute_constraints() {
+    assert_eq!(
+        constraint_violations("{{exercise|task=a|solution=b}}"),
+        vec![]
+    );
+
+    assert_eq!(
+        constraint_violations("{{exercise|question=a|solution=b}}"),
+        vec![("solution".into(), "requires".into(), "solution=b")]
+    );
+
+    // mutually exclusive attributes are only reported once.
+    assert_eq!(
+        constraint_violations("{{exercise|task=a|file=f|link=l}}"),
+        vec![("link".into(), "conflicts_with".into(), "link=l")]
+    );
+
+    let source = "{{exercise|link=l}}";
+    assert_eq!(
+        constraint_violations(source),
+        vec![("".into(), "one_of".into(), source)]
+    );
+    assert_eq!(
+        constraint_violations("{{exercise|question=b|task=a}}"),
+        vec![("question".into(), "one_of".into(), "question=b")]
+    );
+    let template = parse_first_template("{{exercise|task=a|question=b}}");
+    let violations = validate_template(&parse_template(&template).unwrap());
+    assert_eq!(
+        violations[0].cause,
+        "only one of `task`, `question` may be given!"
+    );
+}
+
+/// Template, cause and ancestor kinds of the misplaced templates of a document.
+fn misplacements(source: &str) -> Vec<(String, String, Vec<String>)> {
+    let root = mediawiki_parser::parse(source).unwrap();
+    check_content_model(&root, &spec())
+        .into_iter()
+        .map(|m| {
+            let path = m
+                .path
+                .iter()
+                .map(|p| match p.template {
+                    Some(ref template) => template.clone(),
+                    None => p.kind.clone(),
+                })
+                .collect();
+            (m.template, m.cause, path)
+        })
+        .collect()
+}
+
+#[test]
+fn content_model() {
+    let to_strings = |path: &[&str]| path.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        misplacements("* {{figure|a.png}}\n"),
+        vec![(
+            "Figure".into(),
+            "the Box template `Figure` cannot be placed in a list item!".into(),
+            to_strings(&["Document", "List", "ListItem"])
+        )]
+    );
+    assert_eq!(
+        misplacements("{{exercise|task={{hint|text={{equation|formula=<math>x</math>}}}}}}"),
+        vec![(
+            "Equation".into(),
+            "the Block template `Equation` cannot be placed in the inline template `Hint`!".into(),
+            to_strings(&[
+                "Document",
+                "Exercise",
+                "TemplateArgument",
+                "Hint",
+                "TemplateArgument"
+            ])
+        )]
+    );
+    assert_eq!(misplacements("{{exercise|task={{hint|text=x}}}}"), vec![]);
+    assert_eq!(
+        misplacements("{{hint|text=x}}"),
+        vec![(
+            "Hint".into(),
+            "`Hint` can only be placed in `Exercise`!".into(),
+            to_strings(&["Document"])
+        )]
+    );
+    assert_eq!(
+        misplacements("{{example|example={{figure|a.png}}}}"),
+        vec![(
+            "Figure".into(),
+            "`Figure` cannot be placed in `Example`!".into(),
+            to_strings(&["Document", "Example", "TemplateArgument"])
+        )]
+    );
+    assert_eq!(
+        misplacements("{{example|example={{list|item1=a}}}}"),
+        vec![]
+    );
+
+    // the position is the one of the misplaced template.
+    let source = "text\n\n* a {{figure|a.png}}\n";
+    let root = mediawiki_parser::parse(source).unwrap();
+    let misplaced = check_content_model(&root, &spec());
+    assert_eq!(
+        source_of(source, &misplaced[0].position),
+        "{{figure|a.png}}"
+    );
+}
+
+/// Check a predicate on a source text given as template argument, returning
+/// the cause and source text of a failure.
+fn check_source<'s>(predicate: &Predicate, source: &'s str) -> Result<(), (String, &'s str)> {
+    let prefix = "{{t|x=";
+    let template = parse_first_template(&format!("{}{}}}}}", prefix, source));
+    let content = match template.content.first() {
+        Some(Element::TemplateArgument(arg)) => &arg.value,
+        other => panic!("not an argument: {:?}", other),
+    };
+    predicate(content).map_err(|e| {
+        let tree = e.tree.map(|tree| {
+            let span = tree.get_position();
+            &source[span.start.offset - prefix.len()..span.end.offset - prefix.len()]
+        });
+        (e.cause, tree.unwrap_or_default())
+    })
+}
+
+#[test]
+fn builtin_predicates() {
+    use spec_meta::predicates::*;
+
+    let names: Vec<&str> = builtins().into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names.len(), 8);
+    assert!(names.iter().all(|name| name.starts_with("builtin::")));
+
+    assert_eq!(check_source(&plain_text_only, "just text"), Ok(()));
+    assert_eq!(
+        check_source(&plain_text_only, "a [[link]] b"),
+        Err(("only plain text is allowed here!".into(), "[[link]]"))
+    );
+
+    assert_eq!(check_source(&no_templates, "a ''b''"), Ok(()));
+    assert_eq!(
+        check_source(&no_templates, "a ''{{b}}''"),
+        Err(("templates are not allowed here!".into(), "{{b}}"))
+    );
+
+    assert_eq!(check_source(&no_block_elements, "a ''b''"), Ok(()));
+    assert_eq!(
+        check_source(&no_block_elements, "a\n* b\n").unwrap_err().0,
+        "block elements are not allowed here!"
+    );
+    assert_eq!(
+        check_source(&no_block_elements, "a\n\nb"),
+        Err(("paragraph breaks are not allowed here!".into(), "b"))
+    );
+
+    assert_eq!(
+        check_source(&only_formulas, "<math>a</math> <math>b</math>"),
+        Ok(())
+    );
+    assert_eq!(
+        check_source(&only_formulas, "<math>a</math> b"),
+        Err(("only formulas are allowed here!".into(), " b"))
+    );
+
+    assert_eq!(check_source(&single_paragraph, "a ''b'' c"), Ok(()));
+    assert_eq!(
+        check_source(&single_paragraph, "a\n\nb\n\nc"),
+        Err(("only a single paragraph is allowed here!".into(), "b\n"))
+    );
+
+    assert_eq!(check_source(&non_empty, "a"), Ok(()));
+    assert_eq!(
+        check_source(&non_empty, " <!-- x --> ").unwrap_err().0,
+        "content must not be empty!"
+    );
+    assert_eq!(non_empty(&[]).unwrap_err().tree, None);
+
+    assert_eq!(check_source(&no_headings, "a\n* b\n"), Ok(()));
+    let content = parse_content("== a ==\nb\n");
+    assert_eq!(
+        no_headings(&content).unwrap_err().cause,
+        "headings are not allowed here!"
+    );
+
+    assert_eq!(
+        check_source(&only_file_links, "[[Datei:a.png]] [[File: b.svg|x]]"),
+        Ok(())
+    );
+    assert_eq!(
+        check_source(&only_file_links, "[[File:a.png|[[b]]]]"),
+        Err(("only links to files are allowed here!".into(), "[[b]]"))
+    );
+    assert_eq!(
+        check_source(&only_file_links, "see [https://example.org x]")
+            .unwrap_err()
+            .1,
+        "[https://example.org x]"
+    );
+}
+
+#[test]
+fn predicate_expressions() {
+    let expr = parse_predicate(" all( builtin::non_empty ,max_length(80)) ").unwrap();
+    assert_eq!(
+        expr,
+        parse_predicate("all(builtin::non_empty, max_length(80))").unwrap()
+    );
+    assert_eq!(expr.to_string(), "all(builtin::non_empty, max_length(80))");
+
+    let errors = [
+        ("", "expected a predicate, found the end of input!"),
+        ("all(", "expected a predicate, found the end of input!"),
+        ("all(a b)", "expected \",\" or \")\", found \"b\"!"),
+        ("a)", "unexpected \")\" after the expression!"),
+        ("42", "a number is not a predicate!"),
+        ("a-b", "unexpected character '-'!"),
+        ("builtin:a", "expected a predicate, found \"builtin:a\"!"),
+    ];
+    for &(source, message) in &errors {
+        assert_eq!(
+            parse_predicate(source),
+            Err(message.to_string()),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn max_length() {
+    let template = parse_first_template(&format!(
+        "{{{{example|example=x|title={}}}}}",
+        "x".repeat(81)
+    ));
+    let violations = validate_template(&parse_template(&template).unwrap());
+    let cause = "text must not be longer than 80 characters, found 81!";
+    assert_eq!(violations[0].cause, cause);
+
+    let mut registry: SpecRegistry<Predicate> = SpecRegistry::new();
+    registry.register_predicate("nop_pred", &nop_pred);
+    for (name, predicate) in spec_meta::predicates::builtins() {
+        registry.register_predicate(name, predicate);
+    }
+    registry.load_str(SPEC).unwrap();
+    let title = registry.predicate("Example", "title").unwrap();
+    let call = |predicate: &Predicate, content| {
+        predicate(content).map_err(|e| DynamicPredError {
+            tree: e.tree,
+            cause: e.cause,
+        })
+    };
+    let short = parse_content("A title");
+    assert_eq!(title.check(&short, &call), Ok(()));
+    let long = parse_content(&"x".repeat(81));
+    assert_eq!(title.check(&long, &call).unwrap_err().cause, cause);
+
+    // both count the same text, ignoring markup and surrounding whitespace.
+    let limit = spec_meta::predicates::max_plain_text_length;
+    let content = parse_content(&format!(" ''{}'' {} ", "x".repeat(40), "y".repeat(39)));
+    assert!(limit(&content, 80).is_ok());
+    assert_eq!(
+        DynamicPredicate::<Predicate>::MaxLength(80).check(&content, &call),
+        Ok(())
+    );
+    assert!(limit(&content, 79).is_err());
+    assert!(DynamicPredicate::<Predicate>::MaxLength(79)
+        .check(&content, &call)
+        .is_err());
+}
+
+/// Fails for the first template of a list of elements, without looking further.
+fn no_direct_template(content: &[Element]) -> PredResult<'_> {
+    match content.iter().find(|e| matches!(e, Element::Template(_))) {
+        Some(template) => Err(PredError {
+            tree: Some(template),
+            cause: "no templates!".into(),
+        }),
+        None => Ok(()),
+    }
+}
+
+#[test]
+fn collected_violations() {
+    let source = "a {{list|item1=b {{figure|x.png}} {{hint|text=c}}}} ''{{equation|formula=d}}''";
+    let content = parse_content(source);
+    let expected = vec![
+        "{{list|item1=b {{figure|x.png}} {{hint|text=c}}}} at Paragraph, List",
+        "{{figure|x.png}} at Paragraph, List, TemplateArgument, Figure",
+        "{{equation|formula=d}} at Paragraph, Formatted, Equation",
+    ];
+    let found = |predicate: &Predicate, cause: &str| -> Vec<String> {
+        collect_violations(&content, predicate, &spec())
+            .into_iter()
+            .map(|v| {
+                assert_eq!(v.cause, cause);
+                let path: Vec<String> = v
+                    .path
+                    .into_iter()
+                    .map(|p| p.template.unwrap_or(p.kind))
+                    .collect();
+                format!("{} at {}", source_of(source, &v.position), path.join(", "))
+            })
+            .collect()
+    };
+    // every failure, not only the first one.
+    assert_eq!(found(&no_direct_template, "no templates!"), expected);
+    // recursive predicates fail on every level, but each failure is reported once.
+    assert_eq!(
+        found(
+            &spec_meta::predicates::no_templates,
+            "templates are not allowed here!"
+        ),
+        expected
+    );
+    assert_eq!(
+        collect_violations(&parse_content("a ''b''"), &no_direct_template, &spec()),
+        vec![]
+    );
+}
+
+#[test]
+fn context_predicates() {
+    let source = "== T ==\n{{example|title=T|example=x}}\n";
+    let root = mediawiki_parser::parse(source).unwrap();
+    let heading = match root {
+        Element::Document(ref document) => &document.content[0],
+        ref other => panic!("not a document: {:?}", other),
+    };
+    let template = match heading {
+        Element::Heading(heading) => &heading.content[0],
+        other => panic!("not a heading: {:?}", other),
+    };
+    let known = match template {
+        Element::Template(template) => parse_template(template).unwrap(),
+        other => panic!("not a template: {:?}", other),
+    };
+    let path = vec![&root, heading];
+    let violations = validate_template_at(&known, &path);
+    assert_eq!(violations.len(), 1, "{:?}", violations);
+    assert_eq!(violations[0].predicate_name, "title_not_heading");
+    assert_eq!(violations[0].severity, Severity::Warning);
+    assert_eq!(source_of(source, &violations[0].position), "T");
+    // without ancestors, there is no heading to compare with.
+    assert_eq!(validate_template(&known), vec![]);
+
+    assert_eq!(validate_document(&root), violations);
+    let other = mediawiki_parser::parse("== S ==\n{{example|title=T|example=x}}\n").unwrap();
+    assert_eq!(validate_document(&other), vec![]);
+}
+
+#[test]
+fn combinators() {
+    use spec_meta::predicates::{non_empty, only_formulas};
+
+    let inline = element_kind_is(&["Text", "Formatted"]);
+    assert_eq!(check_source(&inline, "a ''b''"), Ok(()));
+    assert_eq!(
+        check_source(&inline, "a [[b]]"),
+        Err((
+            "expected Text or Formatted, found InternalReference!".into(),
+            "[[b]]"
+        ))
+    );
+
+    let text = and(non_empty, element_kind_is(&["Text"]));
+    assert_eq!(check_source(&text, "a"), Ok(()));
+    assert_eq!(
+        check_source(&text, "").unwrap_err().0,
+        "content must not be empty!"
+    );
+    assert_eq!(check_source(&text, "a ''b''").unwrap_err().1, "''b''");
+
+    let text_or_formula = or(element_kind_is(&["Text"]), only_formulas);
+    assert_eq!(check_source(&text_or_formula, "a"), Ok(()));
+    assert_eq!(check_source(&text_or_formula, "<math>x</math>"), Ok(()));
+    assert_eq!(
+        check_source(&text_or_formula, "a <math>x</math>"),
+        Err((
+            "expected Text, found Formatted! Or: only formulas are allowed here!".into(),
+            "<math>x</math>"
+        ))
+    );
+
+    let markup = not(element_kind_is(&["Text"]), "plain text is not enough!");
+    assert_eq!(check_source(&markup, "''a''"), Ok(()));
+    assert_eq!(
+        check_source(&markup, "a"),
+        Err(("plain text is not enough!".into(), "a"))
+    );
+
+    let no_templates = never(element_kind_is(&["Template"]), "no templates!");
+    assert_eq!(check_source(&no_templates, "a ''b''"), Ok(()));
+    assert_eq!(
+        check_source(&no_templates, "a ''{{b}}''"),
+        Err(("no templates!".into(), "{{b}}"))
+    );
+
+    let emphasis = exists(element_kind_is(&["Formatted"]), "needs emphasis!");
+    assert_eq!(check_source(&emphasis, "a ''b''"), Ok(()));
+    assert_eq!(
+        check_source(&emphasis, "a"),
+        Err(("needs emphasis!".into(), "a"))
+    );
+
+    let all_non_empty = children_only(non_empty);
+    assert_eq!(check_source(&all_non_empty, "''a''"), Ok(()));
+    assert_eq!(
+        check_source(&all_non_empty, "''a'' ''b''"),
+        Err(("content must not be empty!".into(), " "))
+    );
+}
+
+#[test]
+fn severities() {
+    let source = "{{example|example=x}}";
+    let template = parse_first_template(source);
+    let violations = validate_template(&parse_template(&template).unwrap());
+    let found: Vec<(&str, &str, Severity)> = violations
+        .iter()
+        .map(|v| (&v.attribute[..], &v.predicate_name[..], v.severity))
+        .collect();
+    assert_eq!(found, vec![("title", "recommended", Severity::Warning)]);
+    assert_eq!(source_of(source, &violations[0].position), source);
+    assert_eq!(validate_raw_template(&template), Some(violations));
+
+    // the severity of the specification is used for predicate failures.
+    let template = parse_first_template("{{example|example= |title=a ''b''}}");
+    let found: Vec<(String, Severity)> = validate_raw_template(&template)
+        .unwrap()
+        .into_iter()
+        .map(|v| (v.attribute, v.severity))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("title".into(), Severity::Warning),
+            ("example".into(), Severity::Error)
+        ]
+    );
+
+    let example = spec_of("example").unwrap();
+    let attributes: Vec<(&str, Priority, Severity)> = example
+        .attributes
+        .iter()
+        .map(|a| (&a.identifier[..], a.priority, a.severity))
+        .collect();
+    assert_eq!(
+        attributes,
+        vec![
+            ("title", Priority::Recommended, Severity::Warning),
+            ("example", Priority::Required, Severity::Error),
+            ("name", Priority::Optional, Severity::Error),
+        ]
+    );
+}
+
+#[test]
+fn resolve_spec_errors() {
+    let template = |id: &str, extra: &str| {
+        format!(
+            "  - id: {}\n    names: [{}]\n    description: x\n    format: box\n{}    attributes: []\n",
+            id,
+            id.to_lowercase(),
+            extra
+        )
+    };
+    let cycle = format!(
+        "templates:\n{}{}",
+        template("A", "    extends: B\n"),
+        template("B", "    extends: A\n")
+    );
+    assert_eq!(
+        resolve_errors(&cycle),
+        vec![
+            "template \"B\": cyclic inheritance: A -> B -> A!",
+            "template \"A\": cyclic inheritance: B -> A -> B!",
+        ]
+    );
+
+    let unknown = format!(
+        "templates:\n{}{}",
+        template("A", "    extends: C\n"),
+        template("B", "    attribute_groups: [missing]\n")
+    );
+    assert_eq!(
+        resolve_errors(&unknown),
+        vec![
+            "template \"A\": extended template \"C\" does not exist!",
+            "template \"B\": attribute group \"missing\" does not exist!",
+        ]
+    );
+
+    let attribute = |predicate: &str| {
+        format!(
+            "    - id: title\n      names: [title]\n      priority: optional\n      \
+             predicate: {}\n      description: x\n",
+            predicate
+        )
+    };
+    let groups = format!(
+        "attribute_groups:\n  one:\n{}  two:\n{}templates:\n{}",
+        attribute("nop_pred"),
+        attribute("builtin::non_empty"),
+        template("A", "    attribute_groups: [one, two]\n")
+    );
+    assert_eq!(
+        resolve_errors(&groups),
+        vec![
+            "template \"A\", attribute \"title\": conflicting definitions in attribute group \
+             \"one\" and attribute group \"two\", override the attribute to resolve this!"
+        ]
+    );
+    let overridden = groups.replace(
+        "    attributes: []\n",
+        &format!(
+            "    attributes:\n  {}",
+            attribute("nop_pred").replace("\n    ", "\n      ")
+        ),
+    );
+    assert_eq!(resolve_errors(&overridden), Vec::<String>::new());
+}
+
+#[test]
+fn merge_duplicates() {
+    let loaded = |path: &str| LoadedSpec {
+        path: path.into(),
+        source: SPEC.into(),
+        file: SpecFile::from_yaml(SPEC).unwrap(),
+    };
+    let errors = merge_files(&[loaded("a.yml"), loaded("b.yml")]).unwrap_err();
+    let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    assert!(messages.contains(
+        &"template \"Example\": template identifier is also defined in \"a.yml\"!".to_string()
+    ));
+    assert!(messages.iter().any(|m| m.contains("box_common")));
+    assert!(merge_files(&[loaded("a.yml")]).is_ok());
+}
+
+#[test]
+fn load_includes() {
+    let dir = std::env::temp_dir().join(format!("mwparser_utils_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("more")).unwrap();
+    let template = |id: &str| {
+        format!(
+            "  - id: {}\n    names: [{}]\n    description: x\n    format: box\n    attributes: []\n",
+            id,
+            id.to_lowercase()
+        )
+    };
+    let write = |path: &str, source: String| std::fs::write(dir.join(path), source).unwrap();
+    write(
+        "main.yml",
+        format!("include: [more, b.yml]\ntemplates:\n{}", template("A")),
+    );
+    write(
+        "b.yml",
+        format!("include: [main.yml]\ntemplates:\n{}", template("B")),
+    );
+    write("more/d.yml", format!("templates:\n{}", template("D")));
+    write("more/c.yaml", format!("templates:\n{}", template("C")));
+
+    // included directories are read in order of their file names,
+    // files included more than once are read once.
+    let files = load_files(&dir.join("main.yml")).unwrap();
+    let names: Vec<String> = files
+        .iter()
+        .map(|f| f.path.strip_prefix(&dir).unwrap().display().to_string())
+        .collect();
+    assert_eq!(
+        names,
+        vec!["main.yml", "more/c.yaml", "more/d.yml", "b.yml"]
+    );
+    let merged = merge_files(&files).unwrap();
+    let ids: Vec<&str> = merged.templates.iter().map(|t| &t.identifier[..]).collect();
+    assert_eq!(ids, vec!["A", "C", "D", "B"]);
+
+    write("more/e.yml", format!("templates:\n{}", template("C")));
+    let errors = merge_files(&load_files(&dir.join("more")).unwrap()).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].template.as_deref(), Some("C"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
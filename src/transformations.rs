@@ -3,6 +3,9 @@
 use crate::util::{extract_plain_text, find_arg, TexChecker, TexResult};
 use mediawiki_parser::transformations::*;
 use mediawiki_parser::*;
+use mwparser_utils_spec::{SpecAttribute, SpecTemplate};
+use serde_derive::Serialize;
+use std::cell::RefCell;
 
 /// Convert list templates to mediawiki lists.
 pub fn convert_template_list(root: Element) -> TResult {
@@ -112,3 +115,369 @@ fn check_formula(content: &[Element], position: &Span, checker: &TexChecker) ->
         position: position.clone(),
     })
 }
+
+/// A template or argument name replaced by `canonicalize_templates` or `migrate_deprecated`,
+/// or a replacement `migrate_deprecated` skipped because of a conflict.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Rename {
+    /// Identifier of the template.
+    pub template: String,
+    /// Identifier of the renamed attribute, `None` for the template name.
+    pub attribute: Option<String>,
+    pub from: String,
+    pub to: String,
+    pub position: Span,
+}
+
+struct RenameSettings<'s> {
+    templates: &'s [SpecTemplate],
+    reorder: bool,
+    renames: RefCell<Vec<Rename>>,
+    conflicts: RefCell<Vec<Rename>>,
+}
+
+/// Rename templates and their arguments to the default names of the specification.
+///
+/// Repeated arguments keep their number, positional arguments are left unchanged.
+/// With `reorder`, the arguments of known templates are sorted in specification order,
+/// unknown arguments are moved to the end. Returns every rename made.
+#[allow(clippy::result_large_err)]
+pub fn canonicalize_templates(
+    root: Element,
+    templates: &[SpecTemplate],
+    reorder: bool,
+) -> Result<(Element, Vec<Rename>), TransformationError> {
+    let settings = RenameSettings {
+        templates,
+        reorder,
+        renames: RefCell::new(vec![]),
+        conflicts: RefCell::new(vec![]),
+    };
+    let root = canonicalize_templates_rec(root, &settings)?;
+    Ok((root, settings.renames.into_inner()))
+}
+
+/// Rewrite uses of deprecated templates, attributes and names to their replacements.
+///
+/// Arguments of a replaced template are moved to the attributes with the same
+/// identifier in the replacement template, other arguments are left unchanged.
+/// Deprecated templates and attributes without replacement are left unchanged,
+/// a deprecated name without replacement is renamed to the default name.
+/// An argument is not renamed if its replacement attribute is already given,
+/// as this would duplicate the attribute. Returns every rename made and every
+/// rename skipped this way, with identifiers referring to the deprecated
+/// template and attributes.
+#[allow(clippy::result_large_err)]
+pub fn migrate_deprecated(
+    root: Element,
+    templates: &[SpecTemplate],
+) -> Result<(Element, Vec<Rename>, Vec<Rename>), TransformationError> {
+    let settings = RenameSettings {
+        templates,
+        reorder: false,
+        renames: RefCell::new(vec![]),
+        conflicts: RefCell::new(vec![]),
+    };
+    let root = migrate_deprecated_rec(root, &settings)?;
+    Ok((
+        root,
+        settings.renames.into_inner(),
+        settings.conflicts.into_inner(),
+    ))
+}
+
+/// Replace the trimmed part of `name`, keeping surrounding whitespace.
+fn replace_trimmed(name: &str, replacement: &str) -> String {
+    let start = name.len() - name.trim_start().len();
+    let end = name.trim_end().len();
+    format!(
+        "{}{}{}",
+        &name[..start],
+        replacement,
+        &name[end.max(start)..]
+    )
+}
+
+/// The default name of a template or attribute, normalized like the names
+/// of the generated specification.
+fn default_name(names: &[String]) -> String {
+    names[0].trim().to_lowercase()
+}
+
+fn find_template<'s>(templates: &'s [SpecTemplate], name: &str) -> Option<&'s SpecTemplate> {
+    let name = name.trim().to_lowercase();
+    templates
+        .iter()
+        .find(|t| t.names.iter().any(|n| n.trim().to_lowercase() == name))
+}
+
+/// An argument name matched to an attribute of a specification.
+struct ArgumentMatch {
+    /// Index of the attribute.
+    index: usize,
+    /// The matching (lowercase) attribute name, `None` for positional arguments.
+    alias: Option<String>,
+    /// Number of a repeated argument, as given.
+    suffix: String,
+}
+
+impl ArgumentMatch {
+    fn number(&self) -> usize {
+        self.suffix.parse().unwrap_or(0)
+    }
+
+    /// The argument name for another attribute name, keeping the number.
+    fn renamed(&self, name: &str) -> String {
+        format!("{}{}", name, self.suffix)
+    }
+}
+
+fn match_argument(attributes: &[SpecAttribute], name: &str) -> Option<ArgumentMatch> {
+    let name = name.trim().to_lowercase();
+    for (index, attribute) in attributes.iter().enumerate() {
+        for alias in &attribute.names {
+            let alias = alias.trim().to_lowercase();
+            if attribute.repeat {
+                if !name.starts_with(alias.as_str()) {
+                    continue;
+                }
+                let suffix = &name[alias.len()..];
+                if !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit()) {
+                    return Some(ArgumentMatch {
+                        index,
+                        suffix: suffix.to_string(),
+                        alias: Some(alias),
+                    });
+                }
+            } else if alias == name {
+                return Some(ArgumentMatch {
+                    index,
+                    alias: Some(alias),
+                    suffix: String::new(),
+                });
+            }
+        }
+        if attribute.position.map(|p| p.to_string()) == Some(name.clone()) {
+            return Some(ArgumentMatch {
+                index,
+                alias: None,
+                suffix: String::new(),
+            });
+        }
+    }
+    None
+}
+
+/// The name of an argument of attribute `from` when moved to attribute `to`.
+/// `None` if the argument can be kept or cannot be moved.
+fn moved_argument(
+    found: &ArgumentMatch,
+    from: &SpecAttribute,
+    to: &SpecAttribute,
+) -> Option<String> {
+    if from.repeat != to.repeat {
+        return None;
+    }
+    if found.alias.is_none() && from.position == to.position {
+        return None;
+    }
+    Some(found.renamed(&default_name(&to.names)))
+}
+
+fn rename_template(
+    template: &mut Template,
+    spec: &SpecTemplate,
+    to: &str,
+    renames: &mut Vec<Rename>,
+) {
+    let name = extract_plain_text(&template.name);
+    if name.trim() == to {
+        return;
+    }
+    renames.push(Rename {
+        template: spec.identifier.clone(),
+        attribute: None,
+        from: name.trim().to_string(),
+        to: to.to_string(),
+        position: template.position.clone(),
+    });
+    let position = template
+        .name
+        .first()
+        .map(|e| e.get_position().clone())
+        .unwrap_or_else(|| template.position.clone());
+    template.name = vec![Element::Text(Text {
+        position,
+        text: replace_trimmed(&name, to),
+    })];
+}
+
+fn rename_argument(
+    arg: &mut TemplateArgument,
+    spec: &SpecTemplate,
+    attribute: &SpecAttribute,
+    to: &str,
+    renames: &mut Vec<Rename>,
+) {
+    if arg.name.trim() == to {
+        return;
+    }
+    renames.push(Rename {
+        template: spec.identifier.clone(),
+        attribute: Some(attribute.identifier.clone()),
+        from: arg.name.trim().to_string(),
+        to: to.to_string(),
+        position: arg.position.clone(),
+    });
+    arg.name = replace_trimmed(&arg.name, to);
+}
+
+#[allow(clippy::result_large_err)]
+fn canonicalize_templates_rec(mut root: Element, settings: &RenameSettings) -> TResult {
+    if let Element::Template(ref mut template) = root {
+        let spec = find_template(settings.templates, &extract_plain_text(&template.name));
+        if let Some(spec) = spec {
+            let mut renames = settings.renames.borrow_mut();
+            rename_template(template, spec, &default_name(&spec.names), &mut renames);
+
+            let mut order = vec![];
+            for child in &mut template.content {
+                let mut key = (spec.attributes.len(), 0);
+                if let Element::TemplateArgument(ref mut arg) = *child {
+                    if let Some(found) = match_argument(&spec.attributes, &arg.name) {
+                        let attribute = &spec.attributes[found.index];
+                        // positional arguments are left unchanged.
+                        if found.alias.is_some() {
+                            let canonical = found.renamed(&default_name(&attribute.names));
+                            rename_argument(arg, spec, attribute, &canonical, &mut renames);
+                        }
+                        key = (found.index, found.number());
+                    }
+                }
+                order.push(key);
+            }
+
+            if settings.reorder {
+                let mut content: Vec<_> =
+                    order.into_iter().zip(template.content.drain(..)).collect();
+                // stable, so unknown arguments keep their order.
+                content.sort_by_key(|&(key, _)| key);
+                template.content = content.into_iter().map(|(_, child)| child).collect();
+            }
+        }
+    }
+    recurse_inplace(&canonicalize_templates_rec, root, settings)
+}
+
+#[allow(clippy::result_large_err)]
+fn migrate_deprecated_rec(mut root: Element, settings: &RenameSettings) -> TResult {
+    if let Element::Template(ref mut template) = root {
+        let name = extract_plain_text(&template.name).trim().to_lowercase();
+        if let Some(spec) = find_template(settings.templates, &name) {
+            let mut renames = settings.renames.borrow_mut();
+            let replacement = spec
+                .deprecated
+                .as_ref()
+                .and_then(|d| d.replacement.as_ref())
+                .and_then(|id| settings.templates.iter().find(|t| t.identifier == *id));
+
+            if let Some(target) = replacement {
+                rename_template(template, spec, &default_name(&target.names), &mut renames);
+                for child in &mut template.content {
+                    if let Element::TemplateArgument(ref mut arg) = *child {
+                        let found = match match_argument(&spec.attributes, &arg.name) {
+                            Some(found) => found,
+                            None => continue,
+                        };
+                        let attribute = &spec.attributes[found.index];
+                        let moved = target
+                            .attributes
+                            .iter()
+                            .find(|a| a.identifier == attribute.identifier)
+                            .and_then(|to| moved_argument(&found, attribute, to));
+                        if let Some(moved) = moved {
+                            rename_argument(arg, spec, attribute, &moved, &mut renames);
+                        }
+                    }
+                }
+            } else {
+                let deprecated_name = spec
+                    .deprecated_names
+                    .iter()
+                    .find(|(n, _)| n.trim().to_lowercase() == name);
+                if let Some((_, deprecation)) = deprecated_name {
+                    let to = match deprecation.replacement {
+                        Some(ref replacement) => replacement.trim().to_lowercase(),
+                        None => default_name(&spec.names),
+                    };
+                    rename_template(template, spec, &to, &mut renames);
+                }
+
+                // attribute index and number of each given argument.
+                let given: Vec<Option<(usize, usize)>> = template
+                    .content
+                    .iter()
+                    .map(|child| match *child {
+                        Element::TemplateArgument(ref arg) => {
+                            match_argument(&spec.attributes, &arg.name)
+                                .map(|found| (found.index, found.number()))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                for (index, child) in template.content.iter_mut().enumerate() {
+                    if let Element::TemplateArgument(ref mut arg) = *child {
+                        let found = match match_argument(&spec.attributes, &arg.name) {
+                            Some(found) => found,
+                            None => continue,
+                        };
+                        let attribute = &spec.attributes[found.index];
+                        let replacement = attribute
+                            .deprecated
+                            .as_ref()
+                            .and_then(|d| d.replacement.as_ref())
+                            .and_then(|id| {
+                                spec.attributes.iter().position(|a| a.identifier == *id)
+                            });
+                        let deprecated_name = attribute
+                            .deprecated_names
+                            .iter()
+                            .find(|(n, _)| Some(n.trim().to_lowercase()) == found.alias);
+
+                        let (target, moved) = if let Some(to) = replacement {
+                            let moved = moved_argument(&found, attribute, &spec.attributes[to]);
+                            (to, moved)
+                        } else if let Some((_, deprecation)) = deprecated_name {
+                            let to = match deprecation.replacement {
+                                Some(ref replacement) => replacement.trim().to_lowercase(),
+                                None => default_name(&attribute.names),
+                            };
+                            (found.index, Some(found.renamed(&to)))
+                        } else {
+                            continue;
+                        };
+                        let moved = match moved {
+                            Some(moved) => moved,
+                            None => continue,
+                        };
+                        let conflict = given.iter().enumerate().any(|(other, given)| {
+                            other != index && *given == Some((target, found.number()))
+                        });
+                        if conflict {
+                            settings.conflicts.borrow_mut().push(Rename {
+                                template: spec.identifier.clone(),
+                                attribute: Some(attribute.identifier.clone()),
+                                from: arg.name.trim().to_string(),
+                                to: moved,
+                                position: arg.position.clone(),
+                            });
+                        } else {
+                            rename_argument(arg, spec, attribute, &moved, &mut renames);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    recurse_inplace(&migrate_deprecated_rec, root, settings)
+}
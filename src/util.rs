@@ -142,8 +142,9 @@ impl TexChecker for CachedTexChecker {
 }
 
 /// Returns the template argument with a matching name (lowercase) from a list.
+/// Like MediaWiki, the last matching argument is used if there are several.
 pub fn find_arg<'a>(content: &'a [Element], names: &[String]) -> Option<&'a Element> {
-    for child in content {
+    for child in content.iter().rev() {
         if let Element::TemplateArgument(ref e) = *child {
             if names.contains(&e.name.trim().to_lowercase()) {
                 return Some(child);
@@ -152,3 +153,195 @@ pub fn find_arg<'a>(content: &'a [Element], names: &[String]) -> Option<&'a Elem
     }
     None
 }
+
+/// Serialize a list of nodes back to wikitext.
+///
+/// The result is equivalent, but not necessarily identical to the original source.
+/// `Error` nodes are omitted.
+pub fn to_wikitext(content: &[Element]) -> String {
+    let mut result = String::new();
+    write_wikitext(content, "", &mut result);
+    result.trim_end().to_string()
+}
+
+fn write_attributes(attributes: &[TagAttribute], out: &mut String) {
+    for attribute in attributes {
+        out.push_str(&format!(" {}=\"{}\"", attribute.key, attribute.value));
+    }
+}
+
+fn start_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+fn write_wikitext(content: &[Element], list_prefix: &str, out: &mut String) {
+    for root in content {
+        match *root {
+            Element::Document(ref e) => write_wikitext(&e.content, list_prefix, out),
+            Element::Heading(ref e) => {
+                let marker = "=".repeat(e.depth);
+                start_line(out);
+                out.push_str(&format!(
+                    "{} {} {}\n",
+                    marker,
+                    to_wikitext(&e.caption),
+                    marker
+                ));
+                write_wikitext(&e.content, list_prefix, out);
+            }
+            Element::Text(ref e) => out.push_str(&e.text),
+            Element::Formatted(ref e) => {
+                let (open, close) = match e.markup {
+                    MarkupType::NoWiki => ("<nowiki>", "</nowiki>"),
+                    MarkupType::Bold => ("'''", "'''"),
+                    MarkupType::Italic => ("''", "''"),
+                    MarkupType::Math => ("<math>", "</math>"),
+                    MarkupType::StrikeThrough => ("<s>", "</s>"),
+                    MarkupType::Underline => ("<u>", "</u>"),
+                    MarkupType::Code => ("<code>", "</code>"),
+                    MarkupType::Blockquote => ("<blockquote>", "</blockquote>"),
+                    MarkupType::Preformatted => ("<pre>", "</pre>"),
+                };
+                out.push_str(open);
+                write_wikitext(&e.content, list_prefix, out);
+                out.push_str(close);
+            }
+            Element::Paragraph(ref e) => {
+                write_wikitext(&e.content, list_prefix, out);
+                out.push_str("\n\n");
+            }
+            Element::Template(ref e) => {
+                out.push_str("{{");
+                out.push_str(&to_wikitext(&e.name));
+                let mut positional = 0;
+                for argument in &e.content {
+                    out.push('|');
+                    match *argument {
+                        Element::TemplateArgument(ref arg) => {
+                            let value = to_wikitext(&arg.value);
+                            // anonymous arguments are named by their position.
+                            if arg.name == (positional + 1).to_string() && !value.contains('=') {
+                                positional += 1;
+                            } else {
+                                out.push_str(&arg.name);
+                                out.push('=');
+                            }
+                            out.push_str(&value);
+                        }
+                        _ => write_wikitext(std::slice::from_ref(argument), list_prefix, out),
+                    }
+                }
+                out.push_str("}}");
+            }
+            Element::TemplateArgument(ref e) => {
+                out.push_str(&format!("{}={}", e.name, to_wikitext(&e.value)));
+            }
+            Element::InternalReference(ref e) => {
+                out.push_str("[[");
+                out.push_str(&to_wikitext(&e.target));
+                for option in &e.options {
+                    out.push('|');
+                    out.push_str(&to_wikitext(option));
+                }
+                if !e.caption.is_empty() {
+                    out.push('|');
+                    out.push_str(&to_wikitext(&e.caption));
+                }
+                out.push_str("]]");
+            }
+            Element::ExternalReference(ref e) => {
+                out.push('[');
+                out.push_str(&e.target);
+                if !e.caption.is_empty() {
+                    out.push(' ');
+                    out.push_str(&to_wikitext(&e.caption));
+                }
+                out.push(']');
+            }
+            Element::ListItem(ref e) => {
+                let prefix = format!(
+                    "{}{}",
+                    list_prefix,
+                    match e.kind {
+                        ListItemKind::Unordered => '*',
+                        ListItemKind::Ordered => '#',
+                        ListItemKind::Definition => ':',
+                        ListItemKind::DefinitionTerm => ';',
+                    }
+                );
+                start_line(out);
+                out.push_str(&prefix);
+                out.push(' ');
+                write_wikitext(&e.content, &prefix, out);
+                start_line(out);
+            }
+            Element::List(ref e) => {
+                start_line(out);
+                write_wikitext(&e.content, list_prefix, out);
+            }
+            Element::Table(ref e) => {
+                start_line(out);
+                out.push_str("{|");
+                write_attributes(&e.attributes, out);
+                out.push('\n');
+                if !e.caption.is_empty() {
+                    out.push_str("|+");
+                    if !e.caption_attributes.is_empty() {
+                        write_attributes(&e.caption_attributes, out);
+                        out.push_str(" |");
+                    }
+                    out.push_str(&format!(" {}\n", to_wikitext(&e.caption)));
+                }
+                write_wikitext(&e.rows, list_prefix, out);
+                out.push_str("|}\n");
+            }
+            Element::TableRow(ref e) => {
+                out.push_str("|-");
+                write_attributes(&e.attributes, out);
+                out.push('\n');
+                write_wikitext(&e.cells, list_prefix, out);
+            }
+            Element::TableCell(ref e) => {
+                out.push(if e.header { '!' } else { '|' });
+                if !e.attributes.is_empty() {
+                    write_attributes(&e.attributes, out);
+                    out.push_str(" |");
+                }
+                out.push_str(&format!(" {}\n", to_wikitext(&e.content)));
+            }
+            Element::Comment(ref e) => out.push_str(&format!("<!--{}-->", e.text)),
+            Element::HtmlTag(ref e) => {
+                out.push_str(&format!("<{}", e.name));
+                write_attributes(&e.attributes, out);
+                out.push('>');
+                write_wikitext(&e.content, list_prefix, out);
+                out.push_str(&format!("</{}>", e.name));
+            }
+            Element::Gallery(ref e) => {
+                start_line(out);
+                out.push_str("<gallery");
+                write_attributes(&e.attributes, out);
+                out.push_str(">\n");
+                for entry in &e.content {
+                    match *entry {
+                        // gallery entries are written without brackets.
+                        Element::InternalReference(ref reference) => {
+                            let mut parts = vec![to_wikitext(&reference.target)];
+                            parts.extend(reference.options.iter().map(|o| to_wikitext(o)));
+                            if !reference.caption.is_empty() {
+                                parts.push(to_wikitext(&reference.caption));
+                            }
+                            out.push_str(&parts.join("|"));
+                        }
+                        _ => write_wikitext(std::slice::from_ref(entry), list_prefix, out),
+                    }
+                    out.push('\n');
+                }
+                out.push_str("</gallery>\n");
+            }
+            Element::Error(_) => (),
+        }
+    }
+}